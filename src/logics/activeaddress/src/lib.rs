@@ -1,50 +1,72 @@
-use activeaddress_accessors::*;
+use common::{
+    bootstrap::Interval,
+    guard::Outcome,
+    metric::{self, Direction, Metric},
+    trend::{score_trend, trend},
+    weighting::{weighted_mean, Weighting},
+    Args, CalculateInput,
+};
+pub type CalculateArgs = Args;
 #[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
 pub struct LensValue {
-    pub dummy: u64,
+    pub value: f64,
+    pub outcome: Outcome,
+    pub statistic: f64,
+    pub interval: Option<Interval>,
+    pub trend: f64,
+    // growth of the second half of the window over the first
+    pub growth: f64,
+    // whether the log-linear slope behind the trend score is significant at 95%
+    pub significant: bool,
 }
-pub async fn calculate(targets: Vec<String>) -> LensValue {
-    let _result =
-        get_get_last_snapshot_in_sample_snapshot_indexer_icp(targets.get(0usize).unwrap().clone())
-            .await;
-    todo!()
+impl From<CalculateInput> for LensValue {
+    fn from(input: CalculateInput) -> Self {
+        let score = metric::score::<ActiveAddress>(&input);
+        let fitted = trend(&input.values);
+        let trend = score_trend(&input.values, &input.value_all_assets);
+        LensValue {
+            value: score.value,
+            outcome: score.outcome,
+            statistic: metric::statistic::<ActiveAddress>(&input),
+            interval: input
+                .bootstrap
+                .as_ref()
+                .map(|bootstrap| metric::interval::<ActiveAddress>(&input, bootstrap)),
+            trend,
+            growth: fitted.growth,
+            significant: fitted.significant,
+        }
+    }
+}
+
+pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> LensValue {
+    metric::calculate::<ActiveAddress, _>(targets, args).await
 }
 
-fn average_address(data: &[f64]) -> f64 {
+fn average_address(data: &[f64], weighting: &Weighting) -> f64 {
     let n = data.len() as f64;
     if n == 0.0 {
         return 0.0;
     }
 
-    let sum: f64 = data.iter().sum();
-    sum / n
+    weighted_mean(data, weighting)
 }
 
-fn log10_address(data: &[f64]) -> f64 {
-    let average = average_address(data);
-    average.log10()
-}
-
-fn max_log10_address(datasets: &[Vec<f64>]) -> f64 {
-    datasets.iter()
-        .map(|data| log10_address(data))
-        .fold(0.0, f64::max)
-}
+pub struct ActiveAddress;
 
-fn score_address(data: &[f64], datasets: &[Vec<f64>]) -> f64 {
-    let log10_address = log10_address(data);
-    let max_log10_address = max_log10_address(datasets);
+impl Metric for ActiveAddress {
+    const NAME: &'static str = "activeaddress";
+    const DIRECTION: Direction = Direction::HigherIsBetter;
 
-    if max_log10_address == 0.0 {
-        0.0
-    } else {
-        log10_address / max_log10_address
+    fn statistic(data: &[f64], weighting: &Weighting) -> f64 {
+        average_address(data, weighting)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::normalize::Normalization;
 
     const usdc: [f64; 6] = [20756.0, 39127.0, 20996.0, 20644.0, 21952.0, 24694.0];
     const usdt: [f64; 6] = [55211.0, 96979.0, 50291.0, 51362.0, 49945.0, 51539.0];
@@ -55,7 +77,7 @@ mod tests {
     fn test_empty_slice() {
         let data = [];
         let expected = 0.0;
-        let result = average_address(&data);
+        let result = average_address(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -63,7 +85,7 @@ mod tests {
     fn test_all_elements_same() {
         let data = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
         let expected = 1.0;
-        let result = average_address(&data);
+        let result = average_address(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -71,7 +93,7 @@ mod tests {
     fn test_usdc_6days() {
         let data = usdc;
         let expected = 24694.833333333332;
-        let result = average_address(&data);
+        let result = average_address(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -79,7 +101,7 @@ mod tests {
     fn test_usdt_6days() {
         let data = usdt;
         let expected = 59221.166666666664;
-        let result = average_address(&data);
+        let result = average_address(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -87,7 +109,7 @@ mod tests {
     fn test_usdc_log10() {
         let data = usdc;
         let expected = 4.392606099432254;
-        let result = log10_address(&data);
+        let result = ActiveAddress::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -95,7 +117,7 @@ mod tests {
     fn test_usdt_log10() {
         let data = usdt;
         let expected = 4.772476958809861;
-        let result = log10_address(&data);
+        let result = ActiveAddress::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -108,7 +130,13 @@ mod tests {
             fdusd.to_vec(),
         ];
         let expected = 0.9204038358579446;
-        let result = score_address(&usdc, &datasets);
+        let result = ActiveAddress::score(
+            &usdc,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -121,7 +149,13 @@ mod tests {
             fdusd.to_vec(),
         ];
         let expected = 1.0;
-        let result = score_address(&usdt, &datasets);
+        let result = ActiveAddress::score(
+            &usdt,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -134,7 +168,13 @@ mod tests {
             fdusd.to_vec(),
         ];
         let expected = 0.6918397669104942;
-        let result = score_address(&dai, &datasets);
+        let result = ActiveAddress::score(
+            &dai,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -147,8 +187,103 @@ mod tests {
             fdusd.to_vec(),
         ];
         let expected = 0.33338340253051146;
-        let result = score_address(&fdusd, &datasets);
+        let result = ActiveAddress::score(
+            &fdusd,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_slope() {
+        let data = usdc;
+        let expected = -0.025204173644769106;
+        let result = trend(&data).slope;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
-}
 
+    #[test]
+    fn test_trend_usdc() {
+        let datasets = vec![
+            usdc.to_vec(),
+            usdt.to_vec(),
+            dai.to_vec(),
+            fdusd.to_vec(),
+        ];
+        let expected = 0.5;
+        let result = score_trend(&usdc, &datasets);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_trend_fdusd() {
+        let datasets = vec![
+            usdc.to_vec(),
+            usdt.to_vec(),
+            dai.to_vec(),
+            fdusd.to_vec(),
+        ];
+        let expected = 0.5;
+        let result = score_trend(&fdusd, &datasets);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_half_life() {
+        let data = usdc;
+        let expected = 23904.284948778066;
+        let result = average_address(&data, &Weighting::HalfLife(2.0));
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_degenerate_input() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let cases = [
+            (vec![], Outcome::NoData),
+            (vec![0.0, 0.0], Outcome::ZeroInput),
+            (vec![1.0, -1.0], Outcome::NegativeInput),
+            (vec![1.0, f64::NAN], Outcome::NonFinite),
+            (vec![1.0, f64::INFINITY], Outcome::NonFinite),
+        ];
+        for (data, expected) in cases {
+            let result = ActiveAddress::score(
+                &data,
+                &datasets,
+                &Weighting::Uniform,
+                &Normalization::RelativeToMax,
+            );
+            assert_eq!(result.outcome, expected);
+            assert_eq!(result.value, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_score_clamped() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let result = ActiveAddress::score(
+            &[0.5, 0.5],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::Clamped);
+        assert_eq!(result.value, 0.0);
+    }
+
+    #[test]
+    fn test_score_degenerate_peers() {
+        let datasets = vec![[0.5, 0.5].to_vec(), [0.2, 0.2].to_vec()];
+        let result = ActiveAddress::score(
+            &[0.5, 0.5],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::DegeneratePeers);
+        assert_eq!(result.value, 0.0);
+    }
+}
@@ -1,39 +1,58 @@
-use std::{f64::consts::LOG10_E, str::FromStr};
+use std::f64::consts::LOG10_E;
 
 use autocorrelation_accessors::*;
-use candid::Principal;
-use common::{calc, CalculateInput,Args};
+use common::{
+    bootstrap::Interval,
+    guard::Outcome,
+    metric::{self, Direction, Metric},
+    weighting::Weighting,
+    Args, CalculateInput,
+};
 pub type CalculateArgs = Args;
 #[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
 pub struct LensValue {
     pub value: f64,
+    pub outcome: Outcome,
+    pub statistic: f64,
+    pub interval: Option<Interval>,
 }
 
 impl From<CalculateInput> for LensValue {
     fn from(input: CalculateInput) -> Self {
-        let value = input.values;
-        let value_all_assets = input.value_all_assets;
-        let score = score_autocorrelation(&value, &value_all_assets);
-        LensValue { value: score }
+        let score = metric::score::<Autocorrelation>(&input);
+        LensValue {
+            value: score.value,
+            outcome: score.outcome,
+            statistic: metric::statistic::<Autocorrelation>(&input),
+            interval: input
+                .bootstrap
+                .as_ref()
+                .map(|bootstrap| metric::interval::<Autocorrelation>(&input, bootstrap)),
+        }
     }
 }
 
 pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> LensValue {
-    let target = Principal::from_str(&targets[0]).unwrap();
-    calc(target, args).await.unwrap()
+    metric::calculate::<Autocorrelation, _>(targets, args).await
 }
 
-fn autocorrelation(data: &[f64]) -> f64 {
+fn autocorrelation(data: &[f64], weighting: &Weighting) -> f64 {
     if data.len() < 2 {
         return 0.0;
     }
 
-    let numerator: f64 = data.windows(2).map(|w| (w[1] - 1.0) * (w[0] - 1.0)).sum();
+    let weights = weighting.weights(data.len());
+    let numerator: f64 = data
+        .windows(2)
+        .zip(weights.iter().skip(1))
+        .map(|(w, weight)| (w[1] - 1.0) * (w[0] - 1.0) * weight)
+        .sum();
     let denominator: f64 = data
         .iter()
-        .map(|&x| {
+        .zip(weights.iter())
+        .map(|(&x, weight)| {
             let deviation = x - 1.0;
-            deviation * deviation
+            deviation * deviation * weight
         })
         .sum();
 
@@ -44,28 +63,26 @@ fn autocorrelation(data: &[f64]) -> f64 {
     }
 }
 
-fn negative_log10_autocorrelation(data: &[f64]) -> f64 {
-    let autocorrelation = autocorrelation(data);
-    -(autocorrelation + 0.1).ln() * LOG10_E
-}
+pub struct Autocorrelation;
 
-fn max_negative_log10_autocorrelation(datasets: &[Vec<f64>]) -> f64 {
-    datasets
-        .iter()
-        .map(|data| negative_log10_autocorrelation(data))
-        .fold(0.0, f64::max)
-}
+impl Metric for Autocorrelation {
+    const NAME: &'static str = "autocorrelation";
+    const DIRECTION: Direction = Direction::LowerIsBetter;
+    const MIN_SAMPLES: usize = 2;
 
-fn score_autocorrelation(data: &[f64], datasets: &[Vec<f64>]) -> f64 {
-    let log10_deviation = negative_log10_autocorrelation(data);
-    let max_log10_deviation = max_negative_log10_autocorrelation(datasets);
+    fn statistic(data: &[f64], weighting: &Weighting) -> f64 {
+        autocorrelation(data, weighting)
+    }
 
-    log10_deviation / max_log10_deviation
+    fn transform(statistic: f64) -> f64 {
+        -(statistic + 0.1).ln() * LOG10_E
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::normalize::Normalization;
 
     const usdc: [f64; 7] = [
         0.999482, 1.001000, 0.999570, 1.001000, 1.001000, 0.998959, 1.000000,
@@ -84,7 +101,7 @@ mod tests {
     fn test_empty_slice() {
         let data = [];
         let expected = 0.0;
-        let result = autocorrelation(&data);
+        let result = autocorrelation(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -92,7 +109,7 @@ mod tests {
     fn test_all_elements_same() {
         let data = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
         let expected = 0.0;
-        let result = autocorrelation(&data);
+        let result = autocorrelation(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -100,7 +117,7 @@ mod tests {
     fn test_usdc_1week() {
         let data = usdc;
         let expected = 0.3127682858689416;
-        let result = autocorrelation(&data);
+        let result = autocorrelation(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -108,7 +125,7 @@ mod tests {
     fn test_usdt_1week() {
         let data = usdt;
         let expected = 0.0;
-        let result = autocorrelation(&data);
+        let result = autocorrelation(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -116,7 +133,7 @@ mod tests {
     fn test_fdusd_1week() {
         let data = fdusd;
         let expected = 0.017784367377130735;
-        let result = autocorrelation(&data);
+        let result = autocorrelation(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -124,7 +141,7 @@ mod tests {
     fn test_usdc_log10() {
         let data = usdc;
         let expected = 0.3842936781473731;
-        let result = negative_log10_autocorrelation(&data);
+        let result = Autocorrelation::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -132,7 +149,7 @@ mod tests {
     fn test_usdt_log10() {
         let data = usdt;
         let expected = 0.9999999999999999;
-        let result = negative_log10_autocorrelation(&data);
+        let result = Autocorrelation::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -140,7 +157,7 @@ mod tests {
     fn test_fdusd_log10() {
         let data = fdusd;
         let expected = 0.9289123463262241;
-        let result = negative_log10_autocorrelation(&data);
+        let result = Autocorrelation::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -148,7 +165,13 @@ mod tests {
     fn test_score_usdc() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 0.38429367814737314;
-        let result = score_autocorrelation(&usdc, &datasets);
+        let result = Autocorrelation::score(
+            &usdc,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -156,7 +179,13 @@ mod tests {
     fn test_score_usdt() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 1.0;
-        let result = score_autocorrelation(&usdt, &datasets);
+        let result = Autocorrelation::score(
+            &usdt,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -164,7 +193,69 @@ mod tests {
     fn test_score_fdusd() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 0.9289123463262242;
-        let result = score_autocorrelation(&fdusd, &datasets);
+        let result = Autocorrelation::score(
+            &fdusd,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_half_life() {
+        let data = usdc;
+        let expected = 0.3129421519513723;
+        let result = autocorrelation(&data, &Weighting::HalfLife(2.0));
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
+
+    #[test]
+    fn test_score_degenerate_input() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let cases = [
+            (vec![], Outcome::NoData),
+            (vec![0.0, 0.0], Outcome::ZeroInput),
+            (vec![1.0, -1.0], Outcome::NegativeInput),
+            (vec![1.0, f64::NAN], Outcome::NonFinite),
+            (vec![1.0, f64::INFINITY], Outcome::NonFinite),
+        ];
+        for (data, expected) in cases {
+            let result = Autocorrelation::score(
+                &data,
+                &datasets,
+                &Weighting::Uniform,
+                &Normalization::RelativeToMax,
+            );
+            assert_eq!(result.outcome, expected);
+            assert_eq!(result.value, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_score_clamped() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let result = Autocorrelation::score(
+            &[2.0; 20],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::Clamped);
+        assert_eq!(result.value, 0.0);
+    }
+
+    #[test]
+    fn test_score_degenerate_peers() {
+        let datasets = vec![[2.0; 20].to_vec(), [3.0; 20].to_vec()];
+        let result = Autocorrelation::score(
+            &[2.0; 20],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::DegeneratePeers);
+        assert_eq!(result.value, 0.0);
+    }
 }
@@ -0,0 +1,387 @@
+use std::str::FromStr;
+
+use candid::Principal;
+use common::{
+    bootstrap::Interval,
+    call_with_transform,
+    normalize::Normalization,
+    undecodable,
+    weighting::{weighted_mean, Weighting},
+    Args, CalculateInput, SeriesInput,
+};
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct CalculateArgs {
+    pub args: Args,
+    // assets backed by on-chain crypto collateral; the lens only applies to these, and
+    // only these are scored as peers
+    pub crypto_backed: Vec<String>,
+    pub liquidation_threshold: Option<f64>,
+}
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct LensValue {
+    pub value: f64,
+    pub average_ratio: f64,
+    pub minimum_ratio: f64,
+    pub time_near_liquidation: f64,
+    pub interval: Option<Interval>,
+}
+
+// None when the asset is not crypto-backed or has nothing outstanding in the window
+pub async fn calculate(
+    targets: Vec<String>,
+    args: CalculateArgs,
+) -> Result<Option<LensValue>, String> {
+    if !args.crypto_backed.contains(&args.args.id) {
+        return Ok(None);
+    }
+    let target = Principal::from_str(&targets[0]).map_err(|e| e.to_string())?;
+    let threshold = args
+        .liquidation_threshold
+        .unwrap_or(DEFAULT_LIQUIDATION_THRESHOLD);
+    let mut lens_args = args.args;
+    lens_args.ids.retain(|id| args.crypto_backed.contains(id));
+
+    // each snapshot holds the collateral value followed by the outstanding supply
+    let input = call_with_transform(target, lens_args, |f| {
+        let values = f.values().ok_or_else(|| undecodable(&f))?;
+        collateral_ratio(&values)
+    })
+    .await?;
+    let input = outstanding(input);
+    if input.values.is_empty() {
+        return Ok(None);
+    }
+    score_collateral(input, threshold).map(Some)
+}
+
+const DEFAULT_LIQUIDATION_THRESHOLD: f64 = 1.5;
+// ratios within 10% above the liquidation threshold count as near liquidation
+const NEAR_LIQUIDATION_MARGIN: f64 = 0.1;
+// benchmark keys for anchored normalization of the two ratio components
+const AVERAGE_RATIO: &str = "collateralization.average";
+const MINIMUM_RATIO: &str = "collateralization.minimum";
+
+// None when no supply is outstanding, since nothing can then be liquidated
+fn collateral_ratio(values: &[f64]) -> Result<Option<f64>, String> {
+    if values.len() < 2 {
+        return Err(format!(
+            "expected collateral and supply, got {} values",
+            values.len()
+        ));
+    }
+    let (collateral, supply) = (values[0], values[1]);
+    if !(collateral.is_finite() && supply.is_finite()) || collateral < 0.0 || supply < 0.0 {
+        return Err(format!(
+            "collateral {} and supply {} must be finite and non-negative",
+            collateral, supply
+        ));
+    }
+    if supply == 0.0 {
+        return Ok(None);
+    }
+
+    Ok(Some(collateral / supply))
+}
+
+// drops the snapshots without outstanding supply from every series
+fn outstanding(input: SeriesInput<Option<f64>>) -> CalculateInput {
+    CalculateInput {
+        values: input.values.into_iter().flatten().collect(),
+        value_all_assets: input
+            .value_all_assets
+            .into_iter()
+            .map(|data| data.into_iter().flatten().collect())
+            .collect(),
+        weighting: input.weighting,
+        normalization: input.normalization,
+        bootstrap: input.bootstrap,
+        index: input.index,
+    }
+}
+
+fn average_ratio(data: &[f64], weighting: &Weighting) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    weighted_mean(data, weighting)
+}
+
+fn minimum_ratio(data: &[f64]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    data.iter().cloned().fold(f64::INFINITY, f64::min)
+}
+
+fn time_near_liquidation(data: &[f64], threshold: f64, weighting: &Weighting) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let near = threshold * (1.0 + NEAR_LIQUIDATION_MARGIN);
+    let indicator = data
+        .iter()
+        .map(|&x| if x < near { 1.0 } else { 0.0 })
+        .collect::<Vec<f64>>();
+    weighted_mean(&indicator, weighting)
+}
+
+fn score_ratio(
+    data: &[f64],
+    datasets: &[Vec<f64>],
+    component: &str,
+    normalization: &Normalization,
+    stat: impl Fn(&[f64]) -> f64,
+) -> Result<f64, String> {
+    let peers = datasets
+        .iter()
+        .filter(|data| !data.is_empty())
+        .map(|data| stat(data))
+        .collect::<Vec<f64>>();
+    let score = normalization.normalize(component, stat(data), &peers)?;
+    Ok(score.clamp(0.0, 1.0))
+}
+
+struct Components {
+    average_ratio: f64,
+    minimum_ratio: f64,
+    time_near_liquidation: f64,
+}
+
+impl Components {
+    fn value(&self) -> f64 {
+        (self.average_ratio + self.minimum_ratio + self.time_near_liquidation) / 3.0
+    }
+}
+
+fn components(
+    data: &[f64],
+    datasets: &[Vec<f64>],
+    threshold: f64,
+    weighting: &Weighting,
+    normalization: &Normalization,
+) -> Result<Components, String> {
+    Ok(Components {
+        average_ratio: score_ratio(data, datasets, AVERAGE_RATIO, normalization, |data| {
+            average_ratio(data, weighting)
+        })?,
+        minimum_ratio: score_ratio(data, datasets, MINIMUM_RATIO, normalization, minimum_ratio)?,
+        time_near_liquidation: 1.0 - time_near_liquidation(data, threshold, weighting),
+    })
+}
+
+fn score_collateral(input: CalculateInput, threshold: f64) -> Result<LensValue, String> {
+    let score = |data: &[f64], datasets: &[Vec<f64>]| {
+        components(
+            data,
+            datasets,
+            threshold,
+            &input.weighting,
+            &input.normalization,
+        )
+    };
+    let result = score(&input.values, &input.value_all_assets)?;
+    let interval = input.bootstrap.as_ref().map(|bootstrap| {
+        bootstrap.interval(bootstrap.replicates(
+            &input.values,
+            &input.value_all_assets,
+            input.index,
+            |data, datasets| {
+                score(data, datasets)
+                    .map(|components| components.value())
+                    .unwrap_or(f64::NAN)
+            },
+        ))
+    });
+    Ok(LensValue {
+        value: result.value(),
+        average_ratio: result.average_ratio,
+        minimum_ratio: result.minimum_ratio,
+        time_near_liquidation: result.time_near_liquidation,
+        interval,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::bootstrap::Bootstrap;
+
+    const dai: [f64; 7] = [1.82, 1.79, 1.74, 1.61, 1.58, 1.66, 1.71];
+    const lusd: [f64; 7] = [2.45, 2.41, 2.38, 2.12, 2.05, 2.21, 2.30];
+    const crvusd: [f64; 7] = [1.71, 1.68, 1.62, 1.57, 1.52, 1.60, 1.64];
+    const gho: [f64; 7] = [1.95, 1.94, 1.90, 1.83, 1.80, 1.86, 1.88];
+
+    fn input(data: &[f64]) -> CalculateInput {
+        CalculateInput {
+            values: data.to_vec(),
+            value_all_assets: vec![dai.to_vec(), lusd.to_vec(), crvusd.to_vec(), gho.to_vec()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_empty_slice() {
+        let data = [];
+        let expected = 0.0;
+        let result = average_ratio(&data, &Weighting::Uniform);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_zero_supply() {
+        let data = [1000.0, 0.0];
+        let expected = None;
+        let result = collateral_ratio(&data).unwrap();
+        assert_eq!(
+            result, expected,
+            "Expected {:?}, got {:?}",
+            expected, result
+        );
+    }
+
+    #[test]
+    fn test_invalid_snapshot() {
+        assert!(collateral_ratio(&[1000.0]).is_err());
+        assert!(collateral_ratio(&[-1.0, 1.0]).is_err());
+        assert!(collateral_ratio(&[f64::NAN, 1.0]).is_err());
+    }
+
+    #[test]
+    fn test_collateral_ratio() {
+        let data = [5.4e9, 3.6e9];
+        let expected = Some(1.5);
+        let result = collateral_ratio(&data).unwrap();
+        assert_eq!(
+            result, expected,
+            "Expected {:?}, got {:?}",
+            expected, result
+        );
+    }
+
+    #[test]
+    fn test_zero_supply_is_not_near_liquidation() {
+        let input = SeriesInput {
+            values: vec![None, Some(2.0), None],
+            value_all_assets: vec![vec![None, Some(2.0), None], vec![Some(1.8), Some(1.9)]],
+            ..Default::default()
+        };
+        let result = outstanding(input);
+        assert_eq!(result.values, vec![2.0]);
+        assert_eq!(result.value_all_assets, vec![vec![2.0], vec![1.8, 1.9]]);
+        let expected = 0.0;
+        let result = time_near_liquidation(
+            &result.values,
+            DEFAULT_LIQUIDATION_THRESHOLD,
+            &Weighting::Uniform,
+        );
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_dai_minimum_ratio() {
+        let data = dai;
+        let expected = 1.58;
+        let result = minimum_ratio(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_dai_time_near_liquidation() {
+        let data = dai;
+        let expected = 0.2857142857142857;
+        let result =
+            time_near_liquidation(&data, DEFAULT_LIQUIDATION_THRESHOLD, &Weighting::Uniform);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_crvusd_time_near_liquidation() {
+        let data = crvusd;
+        let expected = 0.7142857142857143;
+        let result =
+            time_near_liquidation(&data, DEFAULT_LIQUIDATION_THRESHOLD, &Weighting::Uniform);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_dai() {
+        let expected = 0.7443776664974115;
+        let result = score_collateral(input(&dai), DEFAULT_LIQUIDATION_THRESHOLD)
+            .unwrap()
+            .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_lusd() {
+        let expected = 1.0;
+        let result = score_collateral(input(&lusd), DEFAULT_LIQUIDATION_THRESHOLD)
+            .unwrap()
+            .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_crvusd() {
+        let expected = 0.5798297527124588;
+        let result = score_collateral(input(&crvusd), DEFAULT_LIQUIDATION_THRESHOLD)
+            .unwrap()
+            .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_gho() {
+        let expected = 0.901560648772317;
+        let result = score_collateral(input(&gho), DEFAULT_LIQUIDATION_THRESHOLD)
+            .unwrap()
+            .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_weighting_reaches_components() {
+        let weighted = CalculateInput {
+            weighting: Weighting::HalfLife(1.0),
+            ..input(&dai)
+        };
+        let uniform = score_collateral(input(&dai), DEFAULT_LIQUIDATION_THRESHOLD).unwrap();
+        let result = score_collateral(weighted, DEFAULT_LIQUIDATION_THRESHOLD).unwrap();
+        assert!(result.average_ratio != uniform.average_ratio);
+        assert!(result.time_near_liquidation != uniform.time_near_liquidation);
+    }
+
+    #[test]
+    fn test_normalization_reaches_components() {
+        let ranked = CalculateInput {
+            normalization: Normalization::PercentileRank,
+            ..input(&crvusd)
+        };
+        let expected = 0.125;
+        let result = score_collateral(ranked, DEFAULT_LIQUIDATION_THRESHOLD)
+            .unwrap()
+            .minimum_ratio;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_interval_crvusd() {
+        let bootstrapped = CalculateInput {
+            bootstrap: Some(Bootstrap::default()),
+            ..input(&crvusd)
+        };
+        let result = score_collateral(bootstrapped, DEFAULT_LIQUIDATION_THRESHOLD).unwrap();
+        let interval = result.interval.unwrap();
+        assert!(
+            0.0 <= interval.lower && interval.upper <= 1.0,
+            "got {:?}",
+            interval
+        );
+        assert!(interval.width() > 0.0, "got {:?}", interval);
+        let result = score_collateral(input(&crvusd), DEFAULT_LIQUIDATION_THRESHOLD).unwrap();
+        assert_eq!(result.interval, None);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Encode};
+use ic_stable_structures::{
+    memory_manager::VirtualMemory, storable::Blob, BoundedStorable, DefaultMemoryImpl,
+    StableBTreeMap, Storable,
+};
+use serde::{Deserialize, Serialize};
+
+pub type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+const MAX_METRIC_NAME_SIZE: usize = 32;
+const MAX_BENCHMARK_SIZE: u32 = 128;
+
+type MetricName = Blob<MAX_METRIC_NAME_SIZE>;
+type BenchmarkKey = (MetricName, u32);
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Benchmark {
+    pub version: u32,
+    pub floor: f64,
+    pub ceiling: f64,
+}
+
+impl Benchmark {
+    // freezes the current relative-to-max methodology into a fixed anchor
+    pub fn from_peers(version: u32, peers: &[f64]) -> Self {
+        Benchmark {
+            version,
+            floor: 0.0,
+            ceiling: peers.iter().cloned().fold(0.0, f64::max),
+        }
+    }
+
+    pub fn normalize(&self, value: f64) -> f64 {
+        let range = self.ceiling - self.floor;
+        if range <= 0.0 {
+            return 0.0;
+        }
+
+        ((value - self.floor) / range).clamp(0.0, 1.0)
+    }
+}
+
+impl Storable for Benchmark {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl BoundedStorable for Benchmark {
+    const MAX_SIZE: u32 = MAX_BENCHMARK_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+thread_local! {
+    static BENCHMARKS: RefCell<Option<StableBTreeMap<BenchmarkKey, Benchmark, Memory>>> =
+        const { RefCell::new(None) };
+}
+
+// the canister owns the memory manager and hands over a dedicated virtual memory, so
+// published benchmarks survive upgrades
+pub fn init_benchmarks(memory: Memory) {
+    BENCHMARKS.with(|b| *b.borrow_mut() = Some(StableBTreeMap::init(memory)));
+}
+
+// only the metrics canister initializes the map; the standalone lenses never do
+pub fn benchmarks_initialized() -> bool {
+    BENCHMARKS.with(|b| b.borrow().is_some())
+}
+
+fn metric_name(metric: &str) -> Result<MetricName, String> {
+    MetricName::try_from(metric.as_bytes()).map_err(|_| {
+        format!(
+            "metric name {} exceeds {} bytes",
+            metric, MAX_METRIC_NAME_SIZE
+        )
+    })
+}
+
+fn with_benchmarks<T>(
+    f: impl FnOnce(&mut StableBTreeMap<BenchmarkKey, Benchmark, Memory>) -> T,
+) -> Result<T, String> {
+    BENCHMARKS.with(|b| match b.borrow_mut().as_mut() {
+        Some(benchmarks) => Ok(f(benchmarks)),
+        None => Err("benchmarks are not initialized".to_string()),
+    })
+}
+
+// published versions are immutable so ratings computed against them stay reproducible
+pub fn register_benchmark(metric: &str, benchmark: Benchmark) -> Result<(), String> {
+    let name = metric_name(metric)?;
+    with_benchmarks(|benchmarks| {
+        let latest = benchmarks
+            .range((name, 0)..=(name, u32::MAX))
+            .last()
+            .map(|(_, latest)| latest.version);
+        if let Some(latest) = latest {
+            if benchmark.version <= latest {
+                return Err(format!(
+                    "benchmark version {} for {} must be greater than {}",
+                    benchmark.version, metric, latest
+                ));
+            }
+        }
+        benchmarks.insert((name, benchmark.version), benchmark);
+        Ok(())
+    })?
+}
+
+// None when the version was never published or storage is not initialized
+pub fn benchmark(metric: &str, version: u32) -> Option<Benchmark> {
+    let name = metric_name(metric).ok()?;
+    with_benchmarks(|benchmarks| benchmarks.get(&(name, version)))
+        .ok()
+        .flatten()
+}
+
+pub fn benchmarks(metric: &str) -> Result<Vec<Benchmark>, String> {
+    let name = metric_name(metric)?;
+    with_benchmarks(|benchmarks| {
+        benchmarks
+            .range((name, 0)..=(name, u32::MAX))
+            .map(|(_, benchmark)| benchmark)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ic_stable_structures::memory_manager::{MemoryId, MemoryManager};
+
+    pub(crate) fn init() {
+        let manager = MemoryManager::init(DefaultMemoryImpl::default());
+        init_benchmarks(manager.get(MemoryId::new(0)));
+    }
+
+    #[test]
+    fn test_normalize_clamps() {
+        let benchmark = Benchmark {
+            version: 1,
+            floor: 2.0,
+            ceiling: 6.0,
+        };
+        assert_eq!(benchmark.normalize(4.0), 0.5);
+        assert_eq!(benchmark.normalize(1.0), 0.0);
+        assert_eq!(benchmark.normalize(8.0), 1.0);
+    }
+
+    #[test]
+    fn test_from_peers_matches_relative_to_max() {
+        let benchmark = Benchmark::from_peers(1, &[2.0, 4.0, 8.0]);
+        let expected = 0.75;
+        let result = benchmark.normalize(6.0);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_versions_are_immutable() {
+        init();
+        register_benchmark("test", Benchmark::from_peers(1, &[4.0])).unwrap();
+        register_benchmark("test", Benchmark::from_peers(2, &[8.0])).unwrap();
+        assert!(register_benchmark("test", Benchmark::from_peers(2, &[16.0])).is_err());
+        assert_eq!(benchmark("test", 1).unwrap().ceiling, 4.0);
+        assert_eq!(benchmark("test", 2).unwrap().ceiling, 8.0);
+        assert_eq!(benchmark("test", 3), None);
+        assert_eq!(benchmarks("test").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_metrics_are_kept_apart() {
+        init();
+        register_benchmark("variance", Benchmark::from_peers(3, &[4.0])).unwrap();
+        register_benchmark("deviation", Benchmark::from_peers(1, &[8.0])).unwrap();
+        assert_eq!(benchmarks("variance").unwrap().len(), 1);
+        assert_eq!(benchmark("deviation", 1).unwrap().ceiling, 8.0);
+    }
+
+    #[test]
+    fn test_uninitialized() {
+        BENCHMARKS.with(|b| *b.borrow_mut() = None);
+        assert!(!benchmarks_initialized());
+        assert!(register_benchmark("test", Benchmark::from_peers(1, &[4.0])).is_err());
+        assert_eq!(benchmark("test", 1), None);
+        let name = "x".repeat(MAX_METRIC_NAME_SIZE + 1);
+        assert!(benchmarks(&name).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+// SplitMix64, so every replica of a canister draws the same resamples for the same seed
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+// every replicate rescores the series against every resampled peer, so the count is
+// bounded to keep a request within the instruction limit
+pub const MAX_REPLICATES: u32 = 1000;
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Bootstrap {
+    pub replicates: u32,
+    // defaults to the cube root of the series length
+    pub block_len: Option<u32>,
+    pub confidence: f64,
+    pub seed: u64,
+}
+
+impl Default for Bootstrap {
+    fn default() -> Self {
+        Bootstrap {
+            replicates: 200,
+            block_len: None,
+            confidence: 0.9,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Interval {
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Interval {
+    pub fn width(&self) -> f64 {
+        self.upper - self.lower
+    }
+}
+
+impl Bootstrap {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.replicates == 0 || self.replicates > MAX_REPLICATES {
+            return Err(format!(
+                "replicates must be in [1, {}], got {}",
+                MAX_REPLICATES, self.replicates
+            ));
+        }
+        if self.block_len == Some(0) {
+            return Err("block_len must be positive".to_string());
+        }
+        if !(self.confidence > 0.0 && self.confidence < 1.0) {
+            return Err(format!(
+                "confidence must be in (0, 1), got {}",
+                self.confidence
+            ));
+        }
+        Ok(())
+    }
+
+    fn block_len(&self, n: usize) -> usize {
+        match self.block_len {
+            Some(len) => len as usize,
+            None => (n as f64).cbrt().ceil() as usize,
+        }
+        .clamp(1, n.max(1))
+    }
+
+    // circular block bootstrap: blocks wrap around so every point is drawn equally often
+    pub fn resample<T: Clone>(&self, data: &[T], rng: &mut Rng) -> Vec<T> {
+        let n = data.len();
+        if n == 0 {
+            return vec![];
+        }
+        let block_len = self.block_len(n);
+        let mut sample = Vec::with_capacity(n + block_len);
+        while sample.len() < n {
+            let start = rng.below(n);
+            sample.extend((0..block_len).map(|i| data[(start + i) % n].clone()));
+        }
+        sample.truncate(n);
+        sample
+    }
+
+    // percentile interval of the replicate statistics
+    pub fn interval(&self, mut replicates: Vec<f64>) -> Interval {
+        replicates.retain(|x| x.is_finite());
+        if replicates.is_empty() {
+            return Interval::default();
+        }
+        replicates.sort_by(|a, b| a.total_cmp(b));
+        let tail = (1.0 - self.confidence) / 2.0;
+        let quantile = |q: f64| {
+            let index = (q * (replicates.len() - 1) as f64).round() as usize;
+            replicates[index]
+        };
+        Interval {
+            lower: quantile(tail),
+            upper: quantile(1.0 - tail),
+        }
+    }
+
+    // resamples the series and every peer series, keeping the series' own entry among
+    // the peers, at `index`, identical to its resample; a series may also be of whole
+    // snapshots
+    pub fn replicates<T: Clone>(
+        &self,
+        data: &[T],
+        datasets: &[Vec<T>],
+        index: Option<usize>,
+        statistic: impl Fn(&[T], &[Vec<T>]) -> f64,
+    ) -> Vec<f64> {
+        let mut rng = Rng::new(self.seed);
+        (0..self.replicates)
+            .map(|_| {
+                let sample = self.resample(data, &mut rng);
+                let peers = datasets
+                    .iter()
+                    .enumerate()
+                    .map(|(i, peer)| {
+                        if Some(i) == index {
+                            sample.clone()
+                        } else {
+                            self.resample(peer, &mut rng)
+                        }
+                    })
+                    .collect::<Vec<Vec<T>>>();
+                statistic(&sample, &peers)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const usdc: [f64; 7] = [
+        0.999482, 1.001000, 0.999570, 1.001000, 1.001000, 0.998959, 1.000000,
+    ];
+
+    #[test]
+    fn test_rng_is_deterministic() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let result = (0..5).map(|_| a.next_u64()).collect::<Vec<u64>>();
+        let expected = (0..5).map(|_| b.next_u64()).collect::<Vec<u64>>();
+        assert_eq!(result, expected);
+        assert_ne!(Rng::new(43).next_u64(), result[0]);
+    }
+
+    #[test]
+    fn test_resample_keeps_blocks() {
+        let bootstrap = Bootstrap {
+            block_len: Some(3),
+            ..Default::default()
+        };
+        let data = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let result = bootstrap.resample(&data, &mut Rng::new(7));
+        assert_eq!(result.len(), data.len());
+        // within a block consecutive values follow the circular order
+        for block in result.chunks(3).filter(|block| block.len() > 1) {
+            for pair in block.windows(2) {
+                assert_eq!(pair[1], (pair[0] + 1.0) % 7.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_interval_brackets_estimate() {
+        let bootstrap = Bootstrap::default();
+        let mean = |data: &[f64], _: &[Vec<f64>]| data.iter().sum::<f64>() / data.len() as f64;
+        let estimate = mean(&usdc, &[]);
+        let result = bootstrap.interval(bootstrap.replicates(&usdc, &[], None, mean));
+        assert!(result.lower <= estimate && estimate <= result.upper);
+        assert!(result.width() > 0.0);
+    }
+
+    #[test]
+    fn test_constant_series_has_no_width() {
+        let bootstrap = Bootstrap::default();
+        let data = [1.0; 6];
+        let result = bootstrap.interval(bootstrap.replicates(&data, &[], None, |data, _| data[0]));
+        assert_eq!(
+            result,
+            Interval {
+                lower: 1.0,
+                upper: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_bootstrap() {
+        let bootstrap = Bootstrap {
+            confidence: 1.0,
+            ..Default::default()
+        };
+        assert!(bootstrap.validate().is_err());
+        let bootstrap = Bootstrap {
+            replicates: MAX_REPLICATES + 1,
+            ..Default::default()
+        };
+        assert!(bootstrap.validate().is_err());
+    }
+
+    #[test]
+    fn test_only_own_entry_follows_the_sample() {
+        let bootstrap = Bootstrap {
+            replicates: 20,
+            ..Default::default()
+        };
+        // the twin equals the series in value but is a different asset
+        let datasets = vec![usdc.to_vec(), usdc.to_vec()];
+        let replicates = bootstrap.replicates(&usdc, &datasets, Some(0), |data, peers| {
+            assert_eq!(peers[0], data);
+            if peers[1] == data {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        assert!(replicates.contains(&0.0));
+    }
+}
@@ -0,0 +1,210 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::normalize::Normalization;
+
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, Default, PartialEq)]
+pub enum Outcome {
+    #[default]
+    Valid,
+    // the normalized score fell outside [0, 1] and was clamped
+    Clamped,
+    NoData,
+    InsufficientSamples,
+    ZeroInput,
+    NegativeInput,
+    NonFinite,
+    // no peer yields a positive value to normalize against
+    DegeneratePeers,
+    // anchored normalization names a benchmark version that was never published
+    MissingBenchmark,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, Default, PartialEq)]
+pub struct Guarded {
+    pub value: f64,
+    pub outcome: Outcome,
+}
+
+impl Guarded {
+    fn degenerate(outcome: Outcome) -> Self {
+        Guarded {
+            value: 0.0,
+            outcome,
+        }
+    }
+}
+
+// the six metrics all consume non-negative series: prices, liquidity, counts and volumes
+pub fn classify(data: &[f64]) -> Outcome {
+    if data.is_empty() {
+        Outcome::NoData
+    } else if data.iter().any(|x| !x.is_finite()) {
+        Outcome::NonFinite
+    } else if data.iter().any(|&x| x < 0.0) {
+        Outcome::NegativeInput
+    } else if data.iter().all(|&x| x == 0.0) {
+        Outcome::ZeroInput
+    } else {
+        Outcome::Valid
+    }
+}
+
+pub fn guard_score(score: f64) -> Guarded {
+    if score.is_nan() {
+        Guarded::degenerate(Outcome::NonFinite)
+    } else if !(0.0..=1.0).contains(&score) {
+        Guarded {
+            value: score.clamp(0.0, 1.0),
+            outcome: Outcome::Clamped,
+        }
+    } else {
+        Guarded {
+            value: score,
+            outcome: Outcome::Valid,
+        }
+    }
+}
+
+pub fn guarded_score(
+    metric: &str,
+    min_samples: usize,
+    data: &[f64],
+    datasets: &[Vec<f64>],
+    transform: impl Fn(&[f64]) -> f64,
+    normalization: &Normalization,
+) -> Guarded {
+    let outcome = classify(data);
+    if outcome != Outcome::Valid {
+        return Guarded::degenerate(outcome);
+    }
+    if data.len() < min_samples {
+        return Guarded::degenerate(Outcome::InsufficientSamples);
+    }
+    let value = transform(data);
+    if !value.is_finite() {
+        return Guarded::degenerate(Outcome::NonFinite);
+    }
+
+    let peers = peer_values(min_samples, datasets, transform);
+    if *normalization == Normalization::RelativeToMax && !peers.iter().any(|&x| x > 0.0) {
+        return Guarded::degenerate(Outcome::DegeneratePeers);
+    }
+
+    match normalization.normalize(metric, value, &peers) {
+        Ok(score) => guard_score(score),
+        Err(_) => Guarded::degenerate(Outcome::MissingBenchmark),
+    }
+}
+
+// degenerate peers are left out rather than poisoning the normalization
+pub fn peer_values(
+    min_samples: usize,
+    datasets: &[Vec<f64>],
+    transform: impl Fn(&[f64]) -> f64,
+) -> Vec<f64> {
+    datasets
+        .iter()
+        .filter(|data| classify(data) == Outcome::Valid && data.len() >= min_samples)
+        .map(|data| transform(data))
+        .filter(|x| x.is_finite())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(data: &[f64]) -> f64 {
+        data[0]
+    }
+
+    fn score(data: &[f64], datasets: &[Vec<f64>]) -> Guarded {
+        guarded_score(
+            "test",
+            1,
+            data,
+            datasets,
+            identity,
+            &Normalization::RelativeToMax,
+        )
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(&[]), Outcome::NoData);
+        assert_eq!(classify(&[1.0, f64::NAN]), Outcome::NonFinite);
+        assert_eq!(classify(&[1.0, f64::INFINITY]), Outcome::NonFinite);
+        assert_eq!(classify(&[1.0, -1.0]), Outcome::NegativeInput);
+        assert_eq!(classify(&[0.0, 0.0]), Outcome::ZeroInput);
+        assert_eq!(classify(&[0.0, 1.0]), Outcome::Valid);
+    }
+
+    #[test]
+    fn test_guard_score() {
+        assert_eq!(
+            guard_score(f64::NAN),
+            Guarded::degenerate(Outcome::NonFinite)
+        );
+        assert_eq!(
+            guard_score(-0.5),
+            Guarded {
+                value: 0.0,
+                outcome: Outcome::Clamped
+            }
+        );
+        assert_eq!(
+            guard_score(f64::INFINITY),
+            Guarded {
+                value: 1.0,
+                outcome: Outcome::Clamped
+            }
+        );
+        assert_eq!(
+            guard_score(0.5),
+            Guarded {
+                value: 0.5,
+                outcome: Outcome::Valid
+            }
+        );
+    }
+
+    #[test]
+    fn test_non_finite_transform() {
+        let result = guarded_score(
+            "test",
+            1,
+            &[1.0],
+            &[vec![1.0]],
+            |_| f64::NEG_INFINITY,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result, Guarded::degenerate(Outcome::NonFinite));
+    }
+
+    #[test]
+    fn test_all_negative_peers() {
+        let result = guarded_score(
+            "test",
+            1,
+            &[1.0],
+            &[vec![1.0], vec![2.0]],
+            |data| -data[0],
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result, Guarded::degenerate(Outcome::DegeneratePeers));
+    }
+
+    #[test]
+    fn test_invalid_peers_are_skipped() {
+        let datasets = vec![vec![2.0], vec![f64::INFINITY], vec![-8.0], vec![]];
+        let expected = 0.5;
+        let result = score(&[1.0], &datasets);
+        assert_eq!(
+            result.value, expected,
+            "Expected {}, got {}",
+            expected, result.value
+        );
+        assert_eq!(result.outcome, Outcome::Valid);
+    }
+}
@@ -1,6 +1,18 @@
 use candid::{CandidType, Principal};
 use indexer::{BulkSnapshotIndexerHttps, Snapshot};
 use serde::{Deserialize, Serialize};
+pub mod benchmark;
+pub mod bootstrap;
+pub mod guard;
+pub mod metric;
+pub mod normalize;
+pub mod registry;
+pub mod trend;
+pub mod weighting;
+
+use bootstrap::Bootstrap;
+use normalize::Normalization;
+use weighting::Weighting;
 
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize, Default)]
 pub struct Args {
@@ -8,40 +20,89 @@ pub struct Args {
     pub ids: Vec<String>,
     pub from: Option<i64>,
     pub to: Option<i64>,
+    pub weighting: Option<Weighting>,
+    pub normalization: Option<Normalization>,
+    // confidence intervals are only computed when requested
+    pub bootstrap: Option<Bootstrap>,
+}
+#[derive(Clone, Default)]
+pub struct SeriesInput<T> {
+    pub values: Vec<T>,
+    pub value_all_assets: Vec<Vec<T>>,
+    pub weighting: Weighting,
+    pub normalization: Normalization,
+    pub bootstrap: Option<Bootstrap>,
+    // position of the asset's own series among the peers, matched by id
+    pub index: Option<usize>,
 }
-pub struct CalculateInput {
-    pub values: Vec<f64>,
-    pub value_all_assets: Vec<Vec<f64>>,
+pub type CalculateInput = SeriesInput<f64>;
+
+// error for a snapshot whose value could not be decoded
+pub fn undecodable(snapshot: &Snapshot) -> String {
+    format!("failed to decode the snapshot at {}", snapshot.timestamp())
 }
 
-pub async fn call_with_transform(
+pub async fn call_with_transform<T>(
     target: Principal,
     args: Args,
-    transform: impl Fn(Snapshot) -> f64,
-) -> Result<CalculateInput, String> {
+    transform: impl Fn(Snapshot) -> Result<T, String>,
+) -> Result<SeriesInput<T>, String> {
     let indexer = BulkSnapshotIndexerHttps::new(target);
+    let weighting = args.weighting.unwrap_or_default();
+    let normalization = args.normalization.unwrap_or_default();
+    if let Normalization::Anchored(version) = normalization {
+        if !benchmark::benchmarks_initialized() {
+            return Err(format!(
+                "anchored normalization to version {} is only served by the metrics canister",
+                version
+            ));
+        }
+    }
+    if let Some(bootstrap) = &args.bootstrap {
+        bootstrap.validate()?;
+    }
+    let index = args.ids.iter().position(|id| *id == args.id);
     let value = indexer.query(args.id, args.from, args.to).await?;
     let values = value
         .iter()
         .map(|x| transform(x.clone()))
-        .collect::<Vec<f64>>();
+        .collect::<Result<Vec<T>, String>>()?;
     let mut value_all_assets = vec![];
     for id in args.ids {
         let value = indexer.query(id, args.from, args.to).await?;
         let values = value
             .iter()
             .map(|x| transform(x.clone()))
-            .collect::<Vec<f64>>();
+            .collect::<Result<Vec<T>, String>>()?;
         value_all_assets.push(values);
     }
-    Ok(CalculateInput {
+    Ok(SeriesInput {
         values,
         value_all_assets,
+        weighting,
+        normalization,
+        bootstrap: args.bootstrap,
+        index,
     })
 }
 
+pub async fn query_timestamped(
+    target: Principal,
+    id: String,
+    from: Option<i64>,
+    to: Option<i64>,
+    transform: impl Fn(Snapshot) -> Result<f64, String>,
+) -> Result<Vec<(u64, f64)>, String> {
+    let indexer = BulkSnapshotIndexerHttps::new(target);
+    let value = indexer.query(id, from, to).await?;
+    value
+        .iter()
+        .map(|x| Ok((x.timestamp(), transform(x.clone())?)))
+        .collect::<Result<Vec<(u64, f64)>, String>>()
+}
+
 async fn call(target: Principal, args: Args) -> Result<CalculateInput, String> {
-    call_with_transform(target, args, |x| x.value().unwrap()).await
+    call_with_transform(target, args, |x| x.value().ok_or_else(|| undecodable(&x))).await
 }
 
 pub async fn calc<T: From<CalculateInput>>(target: Principal, args: Args) -> Result<T, String>
@@ -0,0 +1,221 @@
+use std::str::FromStr;
+
+use candid::Principal;
+use indexer::Snapshot;
+
+use crate::{
+    benchmark::{register_benchmark, Benchmark},
+    bootstrap::{Bootstrap, Interval},
+    call_with_transform,
+    guard::{guarded_score, peer_values, Guarded},
+    normalize::Normalization,
+    undecodable,
+    weighting::Weighting,
+    Args, CalculateInput,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    HigherIsBetter,
+    LowerIsBetter,
+}
+
+pub trait Metric {
+    // also the benchmark key for anchored normalization
+    const NAME: &'static str;
+    const DIRECTION: Direction;
+    const MIN_SAMPLES: usize = 1;
+    // smallest statistic the source data can resolve; 0.0 leaves the statistic untouched
+    const RESOLUTION: f64 = 0.0;
+
+    fn statistic(data: &[f64], weighting: &Weighting) -> f64;
+
+    fn transform(statistic: f64) -> f64 {
+        let statistic = statistic.max(Self::RESOLUTION);
+        match Self::DIRECTION {
+            Direction::HigherIsBetter => statistic.log10(),
+            Direction::LowerIsBetter => -statistic.log10(),
+        }
+    }
+
+    fn decode(snapshot: Snapshot) -> Result<f64, String> {
+        snapshot.value().ok_or_else(|| undecodable(&snapshot))
+    }
+
+    fn log10(data: &[f64], weighting: &Weighting) -> f64 {
+        Self::transform(Self::statistic(data, weighting))
+    }
+
+    fn score(
+        data: &[f64],
+        datasets: &[Vec<f64>],
+        weighting: &Weighting,
+        normalization: &Normalization,
+    ) -> Guarded {
+        guarded_score(
+            Self::NAME,
+            Self::MIN_SAMPLES,
+            data,
+            datasets,
+            |data| Self::log10(data, weighting),
+            normalization,
+        )
+    }
+}
+
+pub fn score<M: Metric>(input: &CalculateInput) -> Guarded {
+    M::score(
+        &input.values,
+        &input.value_all_assets,
+        &input.weighting,
+        &input.normalization,
+    )
+}
+
+pub fn statistic<M: Metric>(input: &CalculateInput) -> f64 {
+    M::statistic(&input.values, &input.weighting)
+}
+
+// block-bootstrap interval of the guarded score
+pub fn interval<M: Metric>(input: &CalculateInput, bootstrap: &Bootstrap) -> Interval {
+    let replicates = bootstrap.replicates(
+        &input.values,
+        &input.value_all_assets,
+        input.index,
+        |data, datasets| M::score(data, datasets, &input.weighting, &input.normalization).value,
+    );
+    bootstrap.interval(replicates)
+}
+
+// publishes the current peer set as a benchmark version for anchored normalization
+pub fn freeze<M: Metric>(input: &CalculateInput, version: u32) -> Result<Benchmark, String> {
+    let peers = peer_values(M::MIN_SAMPLES, &input.value_all_assets, |data| {
+        M::log10(data, &input.weighting)
+    });
+    let benchmark = Benchmark::from_peers(version, &peers);
+    register_benchmark(M::NAME, benchmark.clone())?;
+    Ok(benchmark)
+}
+
+pub async fn calculate<M: Metric, V: From<CalculateInput>>(targets: Vec<String>, args: Args) -> V {
+    let target = Principal::from_str(&targets[0]).unwrap();
+    let input = call_with_transform(target, args, M::decode).await.unwrap();
+    V::from(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{benchmark, guard::Outcome};
+
+    struct Spread;
+
+    impl Metric for Spread {
+        const NAME: &'static str = "spread";
+        const DIRECTION: Direction = Direction::LowerIsBetter;
+        const MIN_SAMPLES: usize = 2;
+        const RESOLUTION: f64 = 1e-4;
+
+        fn statistic(data: &[f64], _: &Weighting) -> f64 {
+            let max = data.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let min = data.iter().cloned().fold(f64::INFINITY, f64::min);
+            max - min
+        }
+    }
+
+    #[test]
+    fn test_lower_is_better_transform() {
+        let expected = 2.0;
+        let result = Spread::log10(&[1.0, 1.01], &Weighting::Uniform);
+        assert!(
+            (result - expected).abs() < 1e-9,
+            "Expected {}, got {}",
+            expected,
+            result
+        );
+    }
+
+    #[test]
+    fn test_resolution_floor() {
+        let expected = 4.0;
+        let result = Spread::log10(&[1.0, 1.0], &Weighting::Uniform);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_minimum_samples() {
+        let datasets = vec![vec![1.0, 1.1]];
+        let result = Spread::score(
+            &[1.0],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::InsufficientSamples);
+        assert_eq!(result.value, 0.0);
+    }
+
+    #[test]
+    fn test_score_input() {
+        let input = CalculateInput {
+            values: vec![1.0, 1.01],
+            value_all_assets: vec![vec![1.0, 1.01], vec![1.0, 1.1]],
+            ..Default::default()
+        };
+        let expected = 1.0;
+        let result = score::<Spread>(&input).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_interval() {
+        let input = CalculateInput {
+            values: vec![1.0, 1.01, 1.0, 1.02, 1.0, 1.01],
+            value_all_assets: vec![
+                vec![1.0, 1.01, 1.0, 1.02, 1.0, 1.01],
+                vec![1.0, 1.1, 1.05, 1.0, 1.2, 1.1],
+            ],
+            ..Default::default()
+        };
+        let bootstrap = Bootstrap::default();
+        let estimate = score::<Spread>(&input).value;
+        let result = interval::<Spread>(&input, &bootstrap);
+        assert!(result.lower <= estimate && estimate <= result.upper);
+        assert!((0.0..=1.0).contains(&result.lower) && (0.0..=1.0).contains(&result.upper));
+        assert_eq!(result, interval::<Spread>(&input, &bootstrap));
+    }
+
+    #[test]
+    fn test_freeze_anchors_current_peers() {
+        benchmark::tests::init();
+        let input = CalculateInput {
+            values: vec![1.0, 1.01],
+            value_all_assets: vec![vec![1.0, 1.01], vec![1.0, 1.1], vec![1.0, 1.02]],
+            ..Default::default()
+        };
+        let expected = score::<Spread>(&input).value;
+        freeze::<Spread>(&input, 1).unwrap();
+        assert!(freeze::<Spread>(&input, 1).is_err());
+        let anchored = CalculateInput {
+            value_all_assets: vec![vec![1.0, 1.01]],
+            normalization: Normalization::Anchored(1),
+            ..input
+        };
+        let result = score::<Spread>(&anchored).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_missing_benchmark() {
+        benchmark::tests::init();
+        let input = CalculateInput {
+            values: vec![1.0, 1.01],
+            value_all_assets: vec![vec![1.0, 1.01]],
+            normalization: Normalization::Anchored(2),
+            ..Default::default()
+        };
+        let result = score::<Spread>(&input);
+        assert_eq!(result.outcome, Outcome::MissingBenchmark);
+        assert_eq!(result.value, 0.0);
+    }
+}
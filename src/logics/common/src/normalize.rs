@@ -0,0 +1,197 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::benchmark::benchmark;
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Threshold {
+    pub min: f64,
+    pub score: f64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, Default, PartialEq)]
+pub enum Normalization {
+    #[default]
+    RelativeToMax,
+    MinMax,
+    ZScore,
+    PercentileRank,
+    Thresholds(Vec<Threshold>),
+    // scores against a stored benchmark version instead of the current peer set
+    Anchored(u32),
+}
+
+impl Normalization {
+    pub fn normalize(&self, metric: &str, value: f64, peers: &[f64]) -> Result<f64, String> {
+        Ok(match self {
+            Normalization::RelativeToMax => relative_to_max(value, peers),
+            Normalization::MinMax => min_max(value, peers),
+            Normalization::ZScore => z_score(value, peers),
+            Normalization::PercentileRank => percentile_rank(value, peers),
+            Normalization::Thresholds(thresholds) => threshold_score(value, thresholds),
+            Normalization::Anchored(version) => benchmark(metric, *version)
+                .ok_or(format!("no benchmark version {} for {}", version, metric))?
+                .normalize(value),
+        })
+    }
+}
+
+fn relative_to_max(value: f64, peers: &[f64]) -> f64 {
+    let max = peers.iter().cloned().fold(0.0, f64::max);
+
+    if max == 0.0 {
+        0.0
+    } else {
+        value / max
+    }
+}
+
+fn min_max(value: f64, peers: &[f64]) -> f64 {
+    let min = peers.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = peers.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    if peers.is_empty() || range == 0.0 {
+        0.0
+    } else {
+        ((value - min) / range).clamp(0.0, 1.0)
+    }
+}
+
+fn z_score(value: f64, peers: &[f64]) -> f64 {
+    let n = peers.len() as f64;
+    if n == 0.0 {
+        return 0.5;
+    }
+
+    let mean = peers.iter().sum::<f64>() / n;
+    let variance = peers.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+    if variance == 0.0 {
+        return 0.5;
+    }
+
+    let z = (value - mean) / variance.sqrt();
+    1.0 / (1.0 + (-z).exp())
+}
+
+fn percentile_rank(value: f64, peers: &[f64]) -> f64 {
+    let n = peers.len() as f64;
+    if n == 0.0 {
+        return 0.0;
+    }
+
+    let below = peers.iter().filter(|&&x| x < value).count() as f64;
+    let equal = peers.iter().filter(|&&x| x == value).count() as f64;
+    (below + 0.5 * equal) / n
+}
+
+fn threshold_score(value: f64, thresholds: &[Threshold]) -> f64 {
+    thresholds
+        .iter()
+        .filter(|t| value >= t.min)
+        .max_by(|a, b| a.min.partial_cmp(&b.min).unwrap())
+        .map(|t| t.score)
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::{self, register_benchmark, Benchmark};
+
+    const peers: [f64; 4] = [2.0, 4.0, 6.0, 8.0];
+
+    #[test]
+    fn test_relative_to_max() {
+        let expected = 0.75;
+        let result = Normalization::RelativeToMax
+            .normalize("test", 6.0, &peers)
+            .unwrap();
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_relative_to_max_all_zero() {
+        let expected = 0.0;
+        let result = Normalization::RelativeToMax
+            .normalize("test", 1.0, &[0.0, 0.0])
+            .unwrap();
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_min_max() {
+        let expected = 0.5;
+        let result = Normalization::MinMax
+            .normalize("test", 5.0, &peers)
+            .unwrap();
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_z_score_at_mean() {
+        let expected = 0.5;
+        let result = Normalization::ZScore
+            .normalize("test", 5.0, &peers)
+            .unwrap();
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_z_score_above_mean() {
+        let result = Normalization::ZScore
+            .normalize("test", 8.0, &peers)
+            .unwrap();
+        assert!(result > 0.5 && result < 1.0, "got {}", result);
+    }
+
+    #[test]
+    fn test_percentile_rank() {
+        let expected = 0.625;
+        let result = Normalization::PercentileRank
+            .normalize("test", 6.0, &peers)
+            .unwrap();
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let thresholds = vec![
+            Threshold {
+                min: 0.0,
+                score: 0.2,
+            },
+            Threshold {
+                min: 3.0,
+                score: 0.6,
+            },
+            Threshold {
+                min: 6.0,
+                score: 1.0,
+            },
+        ];
+        let normalization = Normalization::Thresholds(thresholds);
+        assert_eq!(normalization.normalize("test", -1.0, &peers).unwrap(), 0.0);
+        assert_eq!(normalization.normalize("test", 4.0, &peers).unwrap(), 0.6);
+        assert_eq!(normalization.normalize("test", 6.0, &peers).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_anchored_ignores_peers() {
+        benchmark::tests::init();
+        register_benchmark("anchored", Benchmark::from_peers(1, &peers)).unwrap();
+        let normalization = Normalization::Anchored(1);
+        let expected = 0.75;
+        let result = normalization
+            .normalize("anchored", 6.0, &[6.0, 12.0])
+            .unwrap();
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_anchored_missing_version() {
+        benchmark::tests::init();
+        let result = Normalization::Anchored(7).normalize("missing", 6.0, &peers);
+        assert!(result.is_err());
+    }
+}
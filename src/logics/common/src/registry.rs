@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use candid::Principal;
+use indexer::Snapshot;
+
+use crate::{
+    benchmark::Benchmark,
+    bootstrap::{Bootstrap, Interval},
+    call_with_transform,
+    guard::Guarded,
+    metric::{self, Metric},
+    Args, CalculateInput,
+};
+
+pub struct MetricEntry {
+    pub name: &'static str,
+    pub decode: fn(Snapshot) -> Result<f64, String>,
+    pub score: fn(&CalculateInput) -> Guarded,
+    pub statistic: fn(&CalculateInput) -> f64,
+    pub interval: fn(&CalculateInput, &Bootstrap) -> Interval,
+    pub freeze: fn(&CalculateInput, u32) -> Result<Benchmark, String>,
+}
+
+pub struct Evaluation {
+    pub score: Guarded,
+    pub statistic: f64,
+    // only when the args ask for a bootstrap
+    pub interval: Option<Interval>,
+}
+
+impl MetricEntry {
+    pub fn of<M: Metric>() -> Self {
+        MetricEntry {
+            name: M::NAME,
+            decode: M::decode,
+            score: metric::score::<M>,
+            statistic: metric::statistic::<M>,
+            interval: metric::interval::<M>,
+            freeze: metric::freeze::<M>,
+        }
+    }
+
+    // the guarded score together with the raw statistic behind it
+    pub async fn calculate(&self, target: Principal, args: Args) -> Result<Evaluation, String> {
+        let input = call_with_transform(target, args, self.decode).await?;
+        Ok(Evaluation {
+            score: (self.score)(&input),
+            statistic: (self.statistic)(&input),
+            interval: input
+                .bootstrap
+                .as_ref()
+                .map(|bootstrap| (self.interval)(&input, bootstrap)),
+        })
+    }
+
+    // publishes the metric's current peer set as the given benchmark version
+    pub async fn register_benchmark(
+        &self,
+        target: Principal,
+        args: Args,
+        version: u32,
+    ) -> Result<Benchmark, String> {
+        let input = call_with_transform(target, args, self.decode).await?;
+        (self.freeze)(&input, version)
+    }
+}
+
+#[derive(Default)]
+pub struct Registry {
+    entries: BTreeMap<&'static str, MetricEntry>,
+}
+
+impl Registry {
+    pub fn register<M: Metric>(mut self) -> Self {
+        self.entries.insert(M::NAME, MetricEntry::of::<M>());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MetricEntry> {
+        self.entries.get(name)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.entries.keys().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metric::Direction, weighting::Weighting};
+
+    struct Level;
+
+    impl Metric for Level {
+        const NAME: &'static str = "level";
+        const DIRECTION: Direction = Direction::HigherIsBetter;
+
+        fn statistic(data: &[f64], _: &Weighting) -> f64 {
+            data.iter().sum::<f64>() / data.len() as f64
+        }
+    }
+
+    #[test]
+    fn test_lookup_by_name() {
+        let registry = Registry::default().register::<Level>();
+        assert_eq!(registry.names(), vec!["level"]);
+        assert!(registry.get("level").is_some());
+        assert!(registry.get("spread").is_none());
+    }
+
+    #[test]
+    fn test_entry_score_matches_metric() {
+        let input = CalculateInput {
+            values: vec![10.0, 10.0],
+            value_all_assets: vec![vec![10.0, 10.0], vec![100.0, 100.0]],
+            ..Default::default()
+        };
+        let registry = Registry::default().register::<Level>();
+        let expected = metric::score::<Level>(&input);
+        let result = (registry.get("level").unwrap().score)(&input);
+        assert_eq!(result, expected);
+        assert_eq!(result.value, 0.5);
+    }
+}
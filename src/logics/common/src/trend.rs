@@ -0,0 +1,184 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, Default, PartialEq)]
+pub struct Trend {
+    pub slope: f64,
+    pub growth: f64,
+    pub t_statistic: f64,
+    pub significant: bool,
+}
+
+// two-sided 95% critical values of the t distribution for 1..=10 degrees of freedom
+const T_CRITICAL: [f64; 10] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+];
+
+fn t_critical(df: usize) -> f64 {
+    match df {
+        0 => f64::INFINITY,
+        1..=10 => T_CRITICAL[df - 1],
+        11..=20 => 2.086,
+        21..=30 => 2.042,
+        _ => 1.96,
+    }
+}
+
+pub fn trend(data: &[f64]) -> Trend {
+    let points = data
+        .iter()
+        .enumerate()
+        .filter(|(_, &x)| x > 0.0)
+        .map(|(i, &x)| (i as f64, x.ln()))
+        .collect::<Vec<(f64, f64)>>();
+    let (slope, t_statistic) = least_squares(&points);
+    let df = points.len().saturating_sub(2);
+    Trend {
+        slope,
+        growth: period_growth(data),
+        t_statistic,
+        significant: t_statistic.abs() >= t_critical(df),
+    }
+}
+
+// least-squares slope of y on x with its t-statistic, which needs at least three points
+pub fn least_squares(points: &[(f64, f64)]) -> (f64, f64) {
+    let n = points.len() as f64;
+    if n < 2.0 {
+        return (0.0, 0.0);
+    }
+
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    if sxx == 0.0 {
+        return (0.0, 0.0);
+    }
+    let slope = sxy / sxx;
+    if n < 3.0 {
+        return (slope, 0.0);
+    }
+
+    let intercept = mean_y - slope * mean_x;
+    let ssr: f64 = points
+        .iter()
+        .map(|(x, y)| (y - intercept - slope * x).powi(2))
+        .sum();
+    let standard_error = (ssr / (n - 2.0) / sxx).sqrt();
+    if standard_error == 0.0 {
+        if slope == 0.0 {
+            (slope, 0.0)
+        } else {
+            (slope, slope.signum() * f64::INFINITY)
+        }
+    } else {
+        (slope, slope / standard_error)
+    }
+}
+
+fn period_growth(data: &[f64]) -> f64 {
+    let half = data.len() / 2;
+    if half == 0 {
+        return 0.0;
+    }
+
+    let previous = data[..half].iter().sum::<f64>() / half as f64;
+    let current = data[data.len() - half..].iter().sum::<f64>() / half as f64;
+    if previous == 0.0 {
+        0.0
+    } else {
+        current / previous - 1.0
+    }
+}
+
+fn significant_slope(data: &[f64]) -> f64 {
+    let trend = trend(data);
+    if trend.significant {
+        trend.slope
+    } else {
+        0.0
+    }
+}
+
+pub fn score_trend(data: &[f64], datasets: &[Vec<f64>]) -> f64 {
+    let slope = significant_slope(data);
+    let max_slope = datasets
+        .iter()
+        .map(|data| significant_slope(data).abs())
+        .fold(0.0, f64::max);
+
+    if max_slope == 0.0 {
+        0.5
+    } else {
+        (0.5 + 0.5 * slope / max_slope).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_slice() {
+        let expected = Trend::default();
+        let result = trend(&[]);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_all_elements_same() {
+        let data = [5.0, 5.0, 5.0, 5.0, 5.0, 5.0];
+        let result = trend(&data);
+        assert_eq!(result.slope, 0.0);
+        assert_eq!(result.growth, 0.0);
+        assert!(!result.significant);
+    }
+
+    #[test]
+    fn test_exponential_growth() {
+        let data = [1.0, 2.0, 4.0, 8.0, 16.0, 32.0];
+        let result = trend(&data);
+        assert!((result.slope - 2f64.ln()).abs() < 1e-12);
+        assert_eq!(result.growth, 7.0);
+        assert!(result.significant);
+    }
+
+    #[test]
+    fn test_noisy_series_not_significant() {
+        let data = [10.0, 12.0, 9.0, 11.0, 10.0, 12.0];
+        let result = trend(&data);
+        assert!(!result.significant);
+    }
+
+    #[test]
+    fn test_least_squares_points() {
+        let points = [(0.0, 1.0), (1.0, 3.0), (2.0, 5.0), (3.0, 7.0)];
+        assert_eq!(least_squares(&points), (2.0, f64::INFINITY));
+        // every point at the same x has no slope
+        assert_eq!(
+            least_squares(&[(1.0, 1.0), (1.0, 2.0), (1.0, 3.0)]),
+            (0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_score_flat_peers() {
+        let datasets = vec![vec![1.0, 1.0, 1.0], vec![2.0, 2.0, 2.0]];
+        let expected = 0.5;
+        let result = score_trend(&[1.0, 1.0, 1.0], &datasets);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_declining_is_lowest() {
+        let growing = vec![1.0, 2.0, 4.0, 8.0];
+        let declining = vec![8.0, 4.0, 2.0, 1.0];
+        let datasets = vec![growing.clone(), declining.clone()];
+        assert_eq!(score_trend(&growing, &datasets), 1.0);
+        assert_eq!(score_trend(&declining, &datasets), 0.0);
+    }
+}
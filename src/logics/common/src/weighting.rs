@@ -0,0 +1,88 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, Default, PartialEq)]
+pub enum Weighting {
+    #[default]
+    Uniform,
+    HalfLife(f64),
+    Ewma(f64),
+}
+
+impl Weighting {
+    // unnormalized weights, oldest sample first; the most recent sample always weighs 1.0
+    pub fn weights(&self, n: usize) -> Vec<f64> {
+        let decay = match self {
+            Weighting::Uniform => 1.0,
+            Weighting::HalfLife(half_life) if half_life.is_finite() && *half_life > 0.0 => {
+                0.5f64.powf(1.0 / half_life)
+            }
+            Weighting::Ewma(alpha) if *alpha > 0.0 && *alpha < 1.0 => 1.0 - alpha,
+            _ => 1.0,
+        };
+        if decay == 1.0 {
+            return vec![1.0; n];
+        }
+        (0..n).map(|i| decay.powi((n - 1 - i) as i32)).collect()
+    }
+}
+
+pub fn weighted_mean(data: &[f64], weighting: &Weighting) -> f64 {
+    let weights = weighting.weights(data.len());
+    let total: f64 = weights.iter().sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+
+    let sum: f64 = data.iter().zip(weights.iter()).map(|(x, w)| x * w).sum();
+    sum / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_matches_mean() {
+        let data = [0.999482, 1.001000, 0.999570, 1.001000];
+        let expected = data.iter().sum::<f64>() / data.len() as f64;
+        let result = weighted_mean(&data, &Weighting::Uniform);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_empty_slice() {
+        let expected = 0.0;
+        let result = weighted_mean(&[], &Weighting::HalfLife(2.0));
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_half_life_weights() {
+        let expected = vec![0.25, 0.5, 1.0];
+        let result = Weighting::HalfLife(1.0).weights(3);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_ewma_matches_half_life() {
+        let half_life = Weighting::HalfLife(1.0).weights(4);
+        let ewma = Weighting::Ewma(0.5).weights(4);
+        assert_eq!(half_life, ewma);
+    }
+
+    #[test]
+    fn test_recent_samples_dominate() {
+        let data = [1.0, 1.0, 1.0, 1.0, 5.0];
+        let uniform = weighted_mean(&data, &Weighting::Uniform);
+        let decayed = weighted_mean(&data, &Weighting::HalfLife(1.0));
+        assert!(decayed > uniform);
+    }
+
+    #[test]
+    fn test_invalid_half_life_is_uniform() {
+        let expected = vec![1.0, 1.0];
+        let result = Weighting::HalfLife(0.0).weights(2);
+        assert_eq!(result, expected);
+    }
+}
@@ -1,67 +1,68 @@
-use std::str::FromStr;
-
-use candid::Principal;
-use common::{calc, Args, CalculateInput};
+use common::{
+    bootstrap::Interval,
+    guard::Outcome,
+    metric::{self, Direction, Metric},
+    weighting::{weighted_mean, Weighting},
+    Args, CalculateInput,
+};
 pub type CalculateArgs = Args;
 #[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
 pub struct LensValue {
     pub value: f64,
+    pub outcome: Outcome,
+    pub statistic: f64,
+    pub interval: Option<Interval>,
 }
 
 impl From<CalculateInput> for LensValue {
     fn from(input: CalculateInput) -> Self {
-        let value = input.values;
-        let value_all_assets = input.value_all_assets;
-        let score = score_deviation(&value, &value_all_assets);
-        LensValue { value: score }
+        let score = metric::score::<Deviation>(&input);
+        LensValue {
+            value: score.value,
+            outcome: score.outcome,
+            statistic: metric::statistic::<Deviation>(&input),
+            interval: input
+                .bootstrap
+                .as_ref()
+                .map(|bootstrap| metric::interval::<Deviation>(&input, bootstrap)),
+        }
     }
 }
 
 pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> LensValue {
-    let target = Principal::from_str(&targets[0]).unwrap();
-    calc(target, args).await.unwrap()
+    metric::calculate::<Deviation, _>(targets, args).await
 }
 
-fn average_deviation(data: &[f64]) -> f64 {
+fn average_deviation(data: &[f64], weighting: &Weighting) -> f64 {
     let n = data.len() as f64;
     if n == 0.0 {
         return 0.0;
     }
 
-    let deviation = data.iter().map(|&x| (x - 1.0).abs()).sum::<f64>();
-    deviation / n
+    let deviation = data.iter().map(|&x| (x - 1.0).abs()).collect::<Vec<f64>>();
+    weighted_mean(&deviation, weighting)
 }
 
-fn negative_log10_deviation(data: &[f64]) -> f64 {
-    let deviation = average_deviation(data);
-    if deviation == 0.0 {
-        0.0
-    } else {
-        -deviation.log10()
-    }
-}
+// a 6-decimal price tick cannot resolve deviations below one tick, so a perfect peg
+// scores as a one-tick deviation instead of falling off the scale
+const DEVIATION_RESOLUTION: f64 = 1e-6;
 
-fn max_negative_log10_deviation(datasets: &[Vec<f64>]) -> f64 {
-    datasets
-        .iter()
-        .map(|data| negative_log10_deviation(data))
-        .fold(0.0, f64::max)
-}
+pub struct Deviation;
 
-fn score_deviation(data: &[f64], datasets: &[Vec<f64>]) -> f64 {
-    let log10_deviation = negative_log10_deviation(data);
-    let max_log10_deviation = max_negative_log10_deviation(datasets);
+impl Metric for Deviation {
+    const NAME: &'static str = "deviation";
+    const DIRECTION: Direction = Direction::LowerIsBetter;
+    const RESOLUTION: f64 = DEVIATION_RESOLUTION;
 
-    if max_log10_deviation == 0.0 {
-        0.0
-    } else {
-        log10_deviation / max_log10_deviation
+    fn statistic(data: &[f64], weighting: &Weighting) -> f64 {
+        average_deviation(data, weighting)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::normalize::Normalization;
 
     const usdc: [f64; 7] = [
         0.999482, 1.001000, 0.999570, 1.001000, 1.001000, 0.998959, 1.000000,
@@ -80,7 +81,7 @@ mod tests {
     fn test_empty_slice() {
         let data = [];
         let expected = 0.0;
-        let result = average_deviation(&data);
+        let result = average_deviation(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -88,15 +89,42 @@ mod tests {
     fn test_all_elements_same() {
         let data = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
         let expected = 0.0;
-        let result = average_deviation(&data);
+        let result = average_deviation(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
+    #[test]
+    fn test_perfect_peg_log10() {
+        let data = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+        let expected = 6.0;
+        let result = Deviation::log10(&data, &Weighting::Uniform);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_perfect_peg() {
+        let peg = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+        let datasets = vec![peg.to_vec(), usdc.to_vec(), usdt.to_vec(), dai.to_vec()];
+        let expected = 1.0;
+        let result = Deviation::score(
+            &peg,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::Valid);
+        assert_eq!(
+            result.value, expected,
+            "Expected {}, got {}",
+            expected, result.value
+        );
+    }
+
     #[test]
     fn test_usdc_1week() {
         let data = usdc;
         let expected = 0.0007127142857142411;
-        let result = average_deviation(&data);
+        let result = average_deviation(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -104,7 +132,7 @@ mod tests {
     fn test_usdt_1week() {
         let data = usdt;
         let expected = 0.00018028571428569634;
-        let result = average_deviation(&data);
+        let result = average_deviation(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -112,7 +140,7 @@ mod tests {
     fn test_usdc_log10() {
         let data = usdc;
         let expected = 3.1470845360751025;
-        let result = negative_log10_deviation(&data);
+        let result = Deviation::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -120,7 +148,7 @@ mod tests {
     fn test_usdt_log10() {
         let data = usdt;
         let expected = 3.7440386851061844;
-        let result = negative_log10_deviation(&data);
+        let result = Deviation::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -128,7 +156,13 @@ mod tests {
     fn test_score_usdc() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 0.8405587657505329;
-        let result = score_deviation(&usdc, &datasets);
+        let result = Deviation::score(
+            &usdc,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -136,7 +170,13 @@ mod tests {
     fn test_score_usdt() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 1.0;
-        let result = score_deviation(&usdt, &datasets);
+        let result = Deviation::score(
+            &usdt,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -144,7 +184,13 @@ mod tests {
     fn test_score_dai() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 0.8826079539870174;
-        let result = score_deviation(&dai, &datasets);
+        let result = Deviation::score(
+            &dai,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -152,7 +198,69 @@ mod tests {
     fn test_score_fdusd() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 0.734528505276825;
-        let result = score_deviation(&fdusd, &datasets);
+        let result = Deviation::score(
+            &fdusd,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
+
+    #[test]
+    fn test_usdc_half_life() {
+        let data = usdc;
+        let expected = 0.000622881146799291;
+        let result = average_deviation(&data, &Weighting::HalfLife(2.0));
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_degenerate_input() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let cases = [
+            (vec![], Outcome::NoData),
+            (vec![0.0, 0.0], Outcome::ZeroInput),
+            (vec![1.0, -1.0], Outcome::NegativeInput),
+            (vec![1.0, f64::NAN], Outcome::NonFinite),
+            (vec![1.0, f64::INFINITY], Outcome::NonFinite),
+        ];
+        for (data, expected) in cases {
+            let result = Deviation::score(
+                &data,
+                &datasets,
+                &Weighting::Uniform,
+                &Normalization::RelativeToMax,
+            );
+            assert_eq!(result.outcome, expected);
+            assert_eq!(result.value, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_score_clamped() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let result = Deviation::score(
+            &[3.0, 3.0],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::Clamped);
+        assert_eq!(result.value, 0.0);
+    }
+
+    #[test]
+    fn test_score_degenerate_peers() {
+        let datasets = vec![[3.0, 3.0].to_vec(), [5.0, 5.0].to_vec()];
+        let result = Deviation::score(
+            &[3.0, 3.0],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::DegeneratePeers);
+        assert_eq!(result.value, 0.0);
+    }
 }
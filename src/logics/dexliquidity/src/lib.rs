@@ -1,66 +1,80 @@
-use std::str::FromStr;
-
-use candid::Principal;
-use common::{call_with_transform, Args, CalculateInput};
+use common::{
+    bootstrap::Interval,
+    guard::Outcome,
+    metric::{self, Direction, Metric},
+    trend::{score_trend, trend},
+    undecodable,
+    weighting::{weighted_mean, Weighting},
+    Args, CalculateInput,
+};
+use indexer::Snapshot;
 pub type CalculateArgs = Args;
 #[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
 pub struct LensValue {
     pub value: f64,
+    pub outcome: Outcome,
+    pub statistic: f64,
+    pub interval: Option<Interval>,
+    pub trend: f64,
+    // growth of the second half of the window over the first
+    pub growth: f64,
+    // whether the log-linear slope behind the trend score is significant at 95%
+    pub significant: bool,
 }
 impl From<CalculateInput> for LensValue {
     fn from(input: CalculateInput) -> Self {
-        let value = input.values;
-        let value_all_assets = input.value_all_assets;
-        let score = score_liquidity(&value, &value_all_assets);
-        LensValue { value: score }
+        let score = metric::score::<DexLiquidity>(&input);
+        let fitted = trend(&input.values);
+        let trend = score_trend(&input.values, &input.value_all_assets);
+        LensValue {
+            value: score.value,
+            outcome: score.outcome,
+            statistic: metric::statistic::<DexLiquidity>(&input),
+            interval: input
+                .bootstrap
+                .as_ref()
+                .map(|bootstrap| metric::interval::<DexLiquidity>(&input, bootstrap)),
+            trend,
+            growth: fitted.growth,
+            significant: fitted.significant,
+        }
     }
 }
 
 pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> LensValue {
-    let target = Principal::from_str(&targets[0]).unwrap();
-
-    let v = call_with_transform(target, args, |f| f.value_from_string().unwrap())
-        .await
-        .unwrap();
-    LensValue::from(v)
+    metric::calculate::<DexLiquidity, _>(targets, args).await
 }
 
-fn average_liquidity(data: &[f64]) -> f64 {
+fn average_liquidity(data: &[f64], weighting: &Weighting) -> f64 {
     let n = data.len() as f64;
     if n == 0.0 {
         return 0.0;
     }
 
-    let sum: f64 = data.iter().sum();
-    sum / n
+    weighted_mean(data, weighting)
 }
 
-fn log10_liquidity(data: &[f64]) -> f64 {
-    let average = average_liquidity(data);
-    average.log10()
-}
+pub struct DexLiquidity;
 
-fn max_log10_liquidity(datasets: &[Vec<f64>]) -> f64 {
-    datasets
-        .iter()
-        .map(|data| log10_liquidity(data))
-        .fold(0.0, f64::max)
-}
+impl Metric for DexLiquidity {
+    const NAME: &'static str = "dexliquidity";
+    const DIRECTION: Direction = Direction::HigherIsBetter;
 
-fn score_liquidity(data: &[f64], datasets: &[Vec<f64>]) -> f64 {
-    let log10_liquidity = log10_liquidity(data);
-    let max_log10_liquidity = max_log10_liquidity(datasets);
+    fn statistic(data: &[f64], weighting: &Weighting) -> f64 {
+        average_liquidity(data, weighting)
+    }
 
-    if max_log10_liquidity == 0.0 {
-        0.0
-    } else {
-        log10_liquidity / max_log10_liquidity
+    fn decode(snapshot: Snapshot) -> Result<f64, String> {
+        snapshot
+            .value_from_string()
+            .ok_or_else(|| undecodable(&snapshot))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::normalize::Normalization;
 
     const usdc: [f64; 9] = [
         92852142.73,
@@ -97,7 +111,7 @@ mod tests {
     fn test_empty_slice() {
         let data = [];
         let expected = 0.0;
-        let result = average_liquidity(&data);
+        let result = average_liquidity(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -105,7 +119,7 @@ mod tests {
     fn test_all_elements_same() {
         let data = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
         let expected = 1.0;
-        let result = average_liquidity(&data);
+        let result = average_liquidity(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -113,7 +127,7 @@ mod tests {
     fn test_usdc_9days() {
         let data = usdc;
         let expected = 75383053.2811111;
-        let result = average_liquidity(&data);
+        let result = average_liquidity(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -121,7 +135,7 @@ mod tests {
     fn test_usdt_9days() {
         let data = usdt;
         let expected = 5733059.397777777;
-        let result = average_liquidity(&data);
+        let result = average_liquidity(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -129,7 +143,7 @@ mod tests {
     fn test_usdc_log10() {
         let data = usdc;
         let expected = 7.877273723937194;
-        let result = log10_liquidity(&data);
+        let result = DexLiquidity::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -137,7 +151,7 @@ mod tests {
     fn test_usdt_log10() {
         let data = usdt;
         let expected = 6.758386441337469;
-        let result = log10_liquidity(&data);
+        let result = DexLiquidity::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -145,7 +159,13 @@ mod tests {
     fn test_score_usdc() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 1.0;
-        let result = score_liquidity(&usdc, &datasets);
+        let result = DexLiquidity::score(
+            &usdc,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -153,7 +173,13 @@ mod tests {
     fn test_score_usdt() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 0.8579600859622678;
-        let result = score_liquidity(&usdt, &datasets);
+        let result = DexLiquidity::score(
+            &usdt,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -161,7 +187,13 @@ mod tests {
     fn test_score_dai() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 0.8375757434626842;
-        let result = score_liquidity(&dai, &datasets);
+        let result = DexLiquidity::score(
+            &dai,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -169,7 +201,107 @@ mod tests {
     fn test_score_fdusd() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 0.7039182589704108;
-        let result = score_liquidity(&fdusd, &datasets);
+        let result = DexLiquidity::score(
+            &fdusd,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_slope() {
+        let data = usdc;
+        let expected = -0.08634293750895582;
+        let result = trend(&data).slope;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_trend_usdc() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let expected = 0.0;
+        let result = score_trend(&usdc, &datasets);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_trend_fdusd() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let expected = 0.5;
+        let result = score_trend(&fdusd, &datasets);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_lens_reports_trend() {
+        let input = CalculateInput {
+            values: usdc.to_vec(),
+            value_all_assets: vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()],
+            ..Default::default()
+        };
+        let expected = trend(&usdc);
+        let result = LensValue::from(input);
+        assert_eq!(result.growth, expected.growth);
+        assert_eq!(result.significant, expected.significant);
+        assert!(result.significant && result.growth < 0.0);
+    }
+
+    #[test]
+    fn test_usdc_half_life() {
+        let data = usdc;
+        let expected = 62499250.03346156;
+        let result = average_liquidity(&data, &Weighting::HalfLife(2.0));
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
+
+    #[test]
+    fn test_score_degenerate_input() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let cases = [
+            (vec![], Outcome::NoData),
+            (vec![0.0, 0.0], Outcome::ZeroInput),
+            (vec![1.0, -1.0], Outcome::NegativeInput),
+            (vec![1.0, f64::NAN], Outcome::NonFinite),
+            (vec![1.0, f64::INFINITY], Outcome::NonFinite),
+        ];
+        for (data, expected) in cases {
+            let result = DexLiquidity::score(
+                &data,
+                &datasets,
+                &Weighting::Uniform,
+                &Normalization::RelativeToMax,
+            );
+            assert_eq!(result.outcome, expected);
+            assert_eq!(result.value, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_score_clamped() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let result = DexLiquidity::score(
+            &[0.5, 0.5],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::Clamped);
+        assert_eq!(result.value, 0.0);
+    }
+
+    #[test]
+    fn test_score_degenerate_peers() {
+        let datasets = vec![[0.5, 0.5].to_vec(), [0.2, 0.2].to_vec()];
+        let result = DexLiquidity::score(
+            &[0.5, 0.5],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::DegeneratePeers);
+        assert_eq!(result.value, 0.0);
+    }
 }
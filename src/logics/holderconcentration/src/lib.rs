@@ -0,0 +1,428 @@
+use std::str::FromStr;
+
+use candid::Principal;
+use common::{bootstrap::Interval, call_with_transform, undecodable, Args, SeriesInput};
+pub type CalculateArgs = Args;
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct LensValue {
+    pub value: f64,
+    pub gini: f64,
+    pub herfindahl: f64,
+    pub top10_share: f64,
+    pub interval: Option<Interval>,
+}
+impl From<SeriesInput<Vec<f64>>> for LensValue {
+    fn from(input: SeriesInput<Vec<f64>>) -> Self {
+        let (values, peers) = (&input.values, &input.value_all_assets);
+        let gini = score_concentration(values, peers, gini);
+        let herfindahl = score_concentration(values, peers, herfindahl);
+        let top10_share = score_concentration(values, peers, top10_share);
+        // whole snapshots are resampled so each keeps its holder distribution
+        let interval = input.bootstrap.as_ref().map(|bootstrap| {
+            bootstrap.interval(bootstrap.replicates(values, peers, input.index, score))
+        });
+        LensValue {
+            value: (gini + herfindahl + top10_share) / 3.0,
+            gini,
+            herfindahl,
+            top10_share,
+            interval,
+        }
+    }
+}
+
+pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> LensValue {
+    let target = Principal::from_str(&targets[0]).unwrap();
+
+    // each snapshot holds the total supply followed by the balances of the largest holders
+    let v = call_with_transform(target, args, |f| f.values().ok_or_else(|| undecodable(&f)))
+        .await
+        .unwrap();
+    LensValue::from(v)
+}
+
+// concentration is not resolved below one basis point of supply, so an evenly held coin
+// scores as a one-basis-point concentration instead of falling off the scale
+const CONCENTRATION_RESOLUTION: f64 = 1e-4;
+
+// a snapshot's listed holders, with the supply they do not account for assumed to be spread
+// evenly over the fewest holders no larger than the smallest listed one. That is the least
+// concentrated the unlisted remainder can be, so every statistic is a lower bound.
+struct Holders {
+    supply: f64,
+    // listed balances, largest first
+    listed: Vec<f64>,
+    // number of unlisted holders and the balance each of them holds
+    unlisted: f64,
+    unlisted_balance: f64,
+}
+
+fn holders(snapshot: &[f64]) -> Holders {
+    let (supply, balances) = snapshot.split_first().unwrap_or((&0.0, &[]));
+    let mut listed = balances
+        .iter()
+        .cloned()
+        .filter(|x| x.is_finite() && *x > 0.0)
+        .collect::<Vec<f64>>();
+    listed.sort_by(|a, b| b.total_cmp(a));
+
+    let listed_total: f64 = listed.iter().sum();
+    // a supply below the listed balances is taken as fully listed
+    let supply = if supply.is_finite() {
+        supply.max(listed_total)
+    } else {
+        listed_total
+    };
+    let remainder = supply - listed_total;
+    let unlisted = match listed.last() {
+        _ if remainder <= 0.0 => 0.0,
+        Some(smallest) => (remainder / smallest).ceil(),
+        None => 1.0,
+    };
+    Holders {
+        supply,
+        listed,
+        unlisted,
+        unlisted_balance: if unlisted > 0.0 {
+            remainder / unlisted
+        } else {
+            0.0
+        },
+    }
+}
+
+fn gini(snapshot: &[f64]) -> f64 {
+    let holders = holders(snapshot);
+    let k = holders.unlisted;
+    let n = k + holders.listed.len() as f64;
+    if n == 0.0 || holders.supply == 0.0 {
+        return 0.0;
+    }
+
+    // unlisted holders take the k lowest ranks, the listed ones follow in ascending order
+    let unlisted = holders.unlisted_balance * k * (k + 1.0) / 2.0;
+    let listed: f64 = holders
+        .listed
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &x)| (k + (i + 1) as f64) * x)
+        .sum();
+    2.0 * (unlisted + listed) / (n * holders.supply) - (n + 1.0) / n
+}
+
+fn herfindahl(snapshot: &[f64]) -> f64 {
+    let holders = holders(snapshot);
+    if holders.supply == 0.0 {
+        return 0.0;
+    }
+
+    let share = |x: f64| x / holders.supply;
+    let listed: f64 = holders.listed.iter().map(|&x| share(x) * share(x)).sum();
+    let unlisted = holders.unlisted * share(holders.unlisted_balance).powi(2);
+    listed + unlisted
+}
+
+fn top_n_share(snapshot: &[f64], n: usize) -> f64 {
+    let holders = holders(snapshot);
+    if holders.supply == 0.0 {
+        return 0.0;
+    }
+
+    let listed: f64 = holders.listed.iter().take(n).sum();
+    let unlisted = (n.saturating_sub(holders.listed.len()) as f64).min(holders.unlisted)
+        * holders.unlisted_balance;
+    (listed + unlisted) / holders.supply
+}
+
+fn top10_share(snapshot: &[f64]) -> f64 {
+    top_n_share(snapshot, 10)
+}
+
+fn average_concentration(data: &[f64]) -> f64 {
+    let n = data.len() as f64;
+    if n == 0.0 {
+        return 0.0;
+    }
+
+    let sum: f64 = data.iter().sum();
+    sum / n
+}
+
+fn negative_log10_concentration(data: &[f64]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    -average_concentration(data)
+        .max(CONCENTRATION_RESOLUTION)
+        .log10()
+}
+
+fn max_negative_log10_concentration(datasets: &[Vec<f64>]) -> f64 {
+    datasets
+        .iter()
+        .map(|data| negative_log10_concentration(data))
+        .fold(0.0, f64::max)
+}
+
+fn score_concentration(
+    values: &[Vec<f64>],
+    value_all_assets: &[Vec<Vec<f64>>],
+    stat: fn(&[f64]) -> f64,
+) -> f64 {
+    let series = |snapshots: &[Vec<f64>]| snapshots.iter().map(|b| stat(b)).collect::<Vec<f64>>();
+    let data = series(values);
+    let datasets = value_all_assets
+        .iter()
+        .map(|snapshots| series(snapshots))
+        .collect::<Vec<Vec<f64>>>();
+
+    let log10_concentration = negative_log10_concentration(&data);
+    let max_log10_concentration = max_negative_log10_concentration(&datasets);
+
+    if max_log10_concentration == 0.0 {
+        0.0
+    } else {
+        log10_concentration / max_log10_concentration
+    }
+}
+
+fn score(values: &[Vec<f64>], value_all_assets: &[Vec<Vec<f64>>]) -> f64 {
+    let gini = score_concentration(values, value_all_assets, gini);
+    let herfindahl = score_concentration(values, value_all_assets, herfindahl);
+    let top10_share = score_concentration(values, value_all_assets, top10_share);
+    (gini + herfindahl + top10_share) / 3.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // total supply followed by the 15 largest balances, in thousands of tokens
+    const usdc: [[f64; 16]; 3] = [
+        [
+            32.5e6, 964766.6, 433939.6, 307668.2, 199026.8, 171489.9, 135583.8, 107200.4, 101682.5,
+            80943.0, 78378.7, 65373.3, 59677.5, 58621.4, 58446.7, 47025.0,
+        ],
+        [
+            32.5e6, 944647.8, 478406.4, 325394.7, 220993.7, 166749.6, 152597.5, 106931.9, 108810.7,
+            85440.4, 73781.3, 66059.1, 62508.4, 63283.1, 51357.3, 51680.9,
+        ],
+        [
+            32.5e6, 1027782.7, 454610.8, 301504.6, 198606.9, 155270.8, 131132.9, 121838.8,
+            100061.2, 85878.1, 80792.1, 70857.0, 62395.1, 63024.2, 57043.8, 48248.4,
+        ],
+    ];
+    const usdt: [[f64; 16]; 3] = [
+        [
+            110.3e6, 1014884.7, 408172.8, 257728.2, 172507.4, 118172.8, 106715.6, 73597.4, 65888.9,
+            60431.6, 46630.3, 44180.4, 35898.3, 36833.6, 34074.3, 30017.8,
+        ],
+        [
+            110.3e6, 1075095.6, 390997.8, 249105.1, 168051.5, 125378.7, 96512.3, 85102.3, 72943.3,
+            57178.0, 51764.1, 40387.6, 41136.0, 36683.3, 35553.4, 31490.5,
+        ],
+        [
+            110.3e6, 956919.1, 396849.6, 247827.6, 149188.9, 122461.4, 90901.0, 73582.0, 61077.1,
+            60559.1, 46403.3, 42043.1, 38680.1, 38281.8, 29647.2, 29285.0,
+        ],
+    ];
+    const dai: [[f64; 16]; 3] = [
+        [
+            5.35e6, 1009888.0, 576976.8, 395798.1, 308080.0, 224513.0, 195994.4, 168643.0,
+            165718.0, 151085.9, 117103.3, 108061.6, 101113.2, 94112.7, 92720.7, 88959.2,
+        ],
+        [
+            5.35e6, 952549.3, 482736.8, 366010.0, 279665.2, 238040.8, 217438.9, 180156.7, 154369.9,
+            141669.9, 130329.0, 105237.1, 115378.0, 104981.4, 99966.4, 92608.2,
+        ],
+        [
+            5.35e6, 978475.8, 525059.6, 342541.0, 294887.5, 214356.1, 182120.1, 163436.3, 143499.2,
+            133986.8, 114627.1, 103994.8, 99388.9, 91490.7, 90463.5, 79106.9,
+        ],
+    ];
+    const fdusd: [[f64; 16]; 3] = [
+        [
+            2.6e6, 1074866.5, 222602.8, 82924.1, 45019.2, 28106.3, 18884.5, 12785.7, 11028.1,
+            8740.1, 6266.7, 5099.5, 3874.8, 3260.8, 2914.9, 2464.2,
+        ],
+        [
+            2.6e6, 1065771.1, 202900.9, 80686.2, 51638.4, 29155.0, 18039.8, 13948.2, 9333.5,
+            8000.2, 6913.4, 5487.8, 4390.5, 3373.4, 2929.4, 2413.6,
+        ],
+        [
+            2.6e6, 1054387.6, 219056.3, 94171.5, 45752.5, 27385.3, 20621.3, 15170.0, 11035.7,
+            8442.5, 6711.3, 5361.5, 3993.9, 3555.1, 2922.7, 2342.2,
+        ],
+    ];
+
+    fn snapshots(data: &[[f64; 16]]) -> Vec<Vec<f64>> {
+        data.iter().map(|balances| balances.to_vec()).collect()
+    }
+
+    fn input(data: &[[f64; 16]]) -> SeriesInput<Vec<f64>> {
+        SeriesInput {
+            values: snapshots(data),
+            value_all_assets: vec![
+                snapshots(&usdc),
+                snapshots(&usdt),
+                snapshots(&dai),
+                snapshots(&fdusd),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_empty_slice() {
+        let data = [];
+        let expected = 0.0;
+        let result = gini(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_all_elements_same() {
+        let data = [8.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+        let expected = 0.0;
+        let result = gini(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_herfindahl_all_elements_same() {
+        let data = [4.0, 1.0, 1.0, 1.0, 1.0];
+        let expected = 0.25;
+        let result = herfindahl(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_top10_share_few_holders() {
+        let data = [10.0, 5.0, 3.0, 2.0];
+        let expected = 1.0;
+        let result = top10_share(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_unlisted_supply() {
+        // the unlisted 6 is split into two holders of 3, no larger than the listed 4
+        let data = [10.0, 4.0];
+        let expected = 0.34;
+        let result = herfindahl(&data);
+        assert!(
+            (result - expected).abs() < 1e-12,
+            "Expected {}, got {}",
+            expected,
+            result
+        );
+        let expected = 0.4;
+        let result = top_n_share(&data, 1);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+        let expected = 1.0 / 15.0;
+        let result = gini(&data);
+        assert!(
+            (result - expected).abs() < 1e-12,
+            "Expected {}, got {}",
+            expected,
+            result
+        );
+    }
+
+    #[test]
+    fn test_nan_balance() {
+        let data = [10.0, f64::NAN, 4.0];
+        let expected = gini(&[10.0, 4.0]);
+        let result = gini(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+        assert_eq!(top10_share(&data), top10_share(&[10.0, 4.0]));
+    }
+
+    #[test]
+    fn test_even_distribution_scores_top() {
+        // a hundred equal holders, one listed and the rest unlisted
+        let even = vec![vec![100.0, 1.0]; 3];
+        let peers = vec![even.clone(), snapshots(&usdc), snapshots(&fdusd)];
+        let expected = 1.0;
+        let result = score_concentration(&even, &peers, gini);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_gini() {
+        let data = usdc[0];
+        let expected = 0.06612448253393688;
+        let result = gini(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_fdusd_gini() {
+        let data = fdusd[0];
+        let expected = 0.5708969815384615;
+        let result = gini(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_herfindahl() {
+        let data = usdc[0];
+        let expected = 0.0025977990109302207;
+        let result = herfindahl(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_top10_share() {
+        let data = usdc[0];
+        let expected = 0.07940552307692307;
+        let result = top10_share(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_usdc() {
+        let expected = 0.6918901422953058;
+        let result = LensValue::from(input(&usdc)).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_usdt() {
+        let expected = 1.0;
+        let result = LensValue::from(input(&usdt)).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_dai() {
+        let expected = 0.24167135564249723;
+        let result = LensValue::from(input(&dai)).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_fdusd() {
+        let expected = 0.16795954639203411;
+        let result = LensValue::from(input(&fdusd)).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_interval_usdc() {
+        let input = SeriesInput {
+            bootstrap: Some(common::bootstrap::Bootstrap::default()),
+            ..input(&usdc)
+        };
+        let result = LensValue::from(input);
+        let interval = result.interval.unwrap();
+        assert!(interval.lower <= result.value && result.value <= interval.upper);
+        assert!(interval.width() > 0.0, "got {:?}", interval);
+    }
+
+    #[test]
+    fn test_no_interval_without_bootstrap() {
+        assert_eq!(LensValue::from(input(&usdc)).interval, None);
+    }
+}
@@ -58,9 +58,12 @@ struct QueryOptions {
 }
 
 impl Snapshot {
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
     pub fn value(&self) -> Option<f64> {
         let value_f64: Result<Value, bincode::Error> =
-            bincode::deserialize(&self.value.raw.as_slice());
+            bincode::deserialize(self.value.raw.as_slice());
         if value_f64.is_err() {
             ic_cdk::println!("Failed to deserialize value: {:?}", value_f64.err());
             return None;
@@ -69,12 +72,28 @@ impl Snapshot {
     }
     pub fn value_from_string(&self) -> Option<f64> {
         let value_string: Result<DexValue, bincode::Error> =
-            bincode::deserialize(&self.value.raw.as_slice());
+            bincode::deserialize(self.value.raw.as_slice());
         if value_string.is_err() {
             ic_cdk::println!("Failed to deserialize value: {:?}", value_string.err());
             return None;
         }
-        Some(value_string.unwrap().v.parse().unwrap())
+        let value_string = value_string.unwrap().v;
+        match value_string.parse() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                ic_cdk::println!("Failed to parse value {:?}: {:?}", value_string, e);
+                None
+            }
+        }
+    }
+    pub fn values(&self) -> Option<Vec<f64>> {
+        let values: Result<VecValue, bincode::Error> =
+            bincode::deserialize(self.value.raw.as_slice());
+        if values.is_err() {
+            ic_cdk::println!("Failed to deserialize value: {:?}", values.err());
+            return None;
+        }
+        Some(values.unwrap().v)
     }
 }
 
@@ -91,6 +110,10 @@ struct Value {
 struct DexValue {
     v: String,
 }
+#[derive(Deserialize, Serialize)]
+struct VecValue {
+    v: Vec<f64>,
+}
 async fn raw_call_target<T: CandidType + DeserializeOwned>(
     target: Principal,
     method_name: &str,
@@ -105,3 +128,32 @@ async fn raw_call_target<T: CandidType + DeserializeOwned>(
         Err((code, msg)) => Err(format!("Error: {:?}, {:?}", code, msg)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot<T: Serialize>(value: &T) -> Snapshot {
+        Snapshot {
+            id: SnapshotId {
+                id: "usdc".to_string(),
+            },
+            value: SnapshotValue {
+                raw: bincode::serialize(value).unwrap(),
+            },
+            timestamp: 86400,
+        }
+    }
+
+    #[test]
+    fn test_value_from_string() {
+        let parsed = snapshot(&DexValue {
+            v: "92852142.73".to_string(),
+        });
+        assert_eq!(parsed.value_from_string(), Some(92852142.73));
+        let unparsable = snapshot(&DexValue {
+            v: "n/a".to_string(),
+        });
+        assert_eq!(unparsable.value_from_string(), None);
+    }
+}
@@ -0,0 +1,170 @@
+use std::str::FromStr;
+
+use activeaddress::ActiveAddress;
+use autocorrelation::Autocorrelation;
+use candid::Principal;
+use common::{benchmark::Benchmark, bootstrap::Interval, guard::Outcome, registry::Registry, Args};
+use deviation::Deviation;
+use dexliquidity::DexLiquidity;
+use txvolume::TxVolume;
+use variance::Variance;
+
+pub mod stress;
+
+pub use common::benchmark::{benchmarks, init_benchmarks};
+
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct MetricRequest {
+    pub name: String,
+    pub args: Args,
+}
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct CalculateArgs {
+    pub metrics: Vec<MetricRequest>,
+}
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct MetricScore {
+    pub name: String,
+    pub value: f64,
+    pub outcome: Outcome,
+    pub statistic: f64,
+    pub interval: Option<Interval>,
+}
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct MetricError {
+    pub name: String,
+    pub error: String,
+}
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct LensValue {
+    pub scores: Vec<MetricScore>,
+    pub unknown: Vec<String>,
+    // metrics whose series could not be fetched or decoded; the rest of the batch is
+    // still scored
+    pub errors: Vec<MetricError>,
+}
+
+pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> LensValue {
+    let target = Principal::from_str(&targets[0]).unwrap();
+    let registry = registry();
+
+    let mut value = LensValue::default();
+    for request in args.metrics {
+        let entry = match registry.get(&request.name) {
+            Some(entry) => entry,
+            None => {
+                value.unknown.push(request.name);
+                continue;
+            }
+        };
+        match entry.calculate(target, request.args).await {
+            Ok(evaluation) => value.scores.push(MetricScore {
+                name: request.name,
+                value: evaluation.score.value,
+                outcome: evaluation.score.outcome,
+                statistic: evaluation.statistic,
+                interval: evaluation.interval,
+            }),
+            Err(error) => value.errors.push(MetricError {
+                name: request.name,
+                error,
+            }),
+        }
+    }
+    value
+}
+
+// a benchmark version published from the metric's current peer set
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct BenchmarkRequest {
+    pub name: String,
+    pub args: Args,
+    pub version: u32,
+}
+
+pub async fn register_benchmark(
+    targets: Vec<String>,
+    request: BenchmarkRequest,
+) -> Result<Benchmark, String> {
+    // published versions are immutable, so only a controller may publish one
+    let caller = ic_cdk::caller();
+    if !ic_cdk::api::is_controller(&caller) {
+        return Err(format!("{} is not a controller", caller));
+    }
+    let target = Principal::from_str(&targets[0]).map_err(|e| e.to_string())?;
+    let registry = registry();
+    let entry = registry
+        .get(&request.name)
+        .ok_or(format!("unknown metric {}", request.name))?;
+    entry
+        .register_benchmark(target, request.args, request.version)
+        .await
+}
+
+pub fn registry() -> Registry {
+    Registry::default()
+        .register::<Deviation>()
+        .register::<Variance>()
+        .register::<Autocorrelation>()
+        .register::<DexLiquidity>()
+        .register::<ActiveAddress>()
+        .register::<TxVolume>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{
+        metric::{self, Metric},
+        CalculateInput,
+    };
+
+    const usdc: [f64; 7] = [
+        0.999482, 1.001000, 0.999570, 1.001000, 1.001000, 0.998959, 1.000000,
+    ];
+    const usdt: [f64; 7] = [
+        1.000000, 1.000000, 0.999800, 1.000000, 1.000600, 1.000000, 0.999800,
+    ];
+
+    fn input() -> CalculateInput {
+        CalculateInput {
+            values: usdc.to_vec(),
+            value_all_assets: vec![usdc.to_vec(), usdt.to_vec()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_registered_metrics() {
+        let expected = vec![
+            "activeaddress",
+            "autocorrelation",
+            "deviation",
+            "dexliquidity",
+            "txvolume",
+            "variance",
+        ];
+        let result = registry().names();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_unknown_metric() {
+        assert!(registry().get("sharpe").is_none());
+    }
+
+    #[test]
+    fn test_score_by_name() {
+        let registry = registry();
+        for name in [Deviation::NAME, Variance::NAME, Autocorrelation::NAME] {
+            let entry = registry.get(name).unwrap();
+            let result = (entry.score)(&input());
+            let expected = match name {
+                Deviation::NAME => metric::score::<Deviation>(&input()),
+                Variance::NAME => metric::score::<Variance>(&input()),
+                _ => metric::score::<Autocorrelation>(&input()),
+            };
+            assert_eq!(result, expected);
+        }
+    }
+}
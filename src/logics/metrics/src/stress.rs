@@ -0,0 +1,508 @@
+use std::str::FromStr;
+
+use activeaddress::ActiveAddress;
+use autocorrelation::Autocorrelation;
+use candid::{CandidType, Principal};
+use common::{
+    call_with_transform,
+    metric::{self, Metric},
+    Args, CalculateInput,
+};
+use deviation::Deviation;
+use dexliquidity::DexLiquidity;
+use rating_indexer::{rating_of, Aggregator, Grade};
+use serde::{Deserialize, Serialize};
+use txvolume::TxVolume;
+use variance::Variance;
+
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub enum Series {
+    Price,
+    Liquidity,
+    ActiveAddresses,
+    TxVolume,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub enum Shock {
+    // holds the last `days` values at `level`
+    Level {
+        series: Series,
+        level: f64,
+        days: u32,
+    },
+    // multiplies the last `days` values by `factor`, or the whole series when unset
+    Scale {
+        series: Series,
+        factor: f64,
+        days: Option<u32>,
+    },
+    // multiplies the tail of the series day by day, the path's first factor landing
+    // `min(path, series)` days before the end
+    Path {
+        series: Series,
+        factors: Vec<f64>,
+    },
+}
+
+impl Shock {
+    fn series(&self) -> Series {
+        match self {
+            Shock::Level { series, .. }
+            | Shock::Scale { series, .. }
+            | Shock::Path { series, .. } => *series,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let values = match self {
+            Shock::Level { level, .. } => std::slice::from_ref(level),
+            Shock::Scale { factor, .. } => std::slice::from_ref(factor),
+            Shock::Path { factors, .. } => factors.as_slice(),
+        };
+        if values
+            .iter()
+            .any(|value| !value.is_finite() || *value < 0.0)
+        {
+            return Err(format!(
+                "shock values must be finite and non-negative: {:?}",
+                self
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, data: &[f64]) -> Vec<f64> {
+        let tail =
+            |days: Option<u32>| days.map_or(0, |days| data.len().saturating_sub(days as usize));
+        let mut shocked = data.to_vec();
+        match self {
+            Shock::Level { level, days, .. } => {
+                let start = tail(Some(*days));
+                shocked[start..].iter_mut().for_each(|x| *x = *level);
+            }
+            Shock::Scale { factor, days, .. } => {
+                let start = tail(*days);
+                shocked[start..].iter_mut().for_each(|x| *x *= factor);
+            }
+            Shock::Path { factors, .. } => {
+                let start = tail(Some(factors.len() as u32));
+                shocked[start..]
+                    .iter_mut()
+                    .zip(factors.iter())
+                    .for_each(|(x, factor)| *x *= factor);
+            }
+        }
+        shocked
+    }
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    pub shocks: Vec<Shock>,
+}
+
+// the series behind the six rating components, each with its peer series
+#[derive(Clone, Default)]
+pub struct AssetSeries {
+    pub price: CalculateInput,
+    pub liquidity: CalculateInput,
+    pub active_addresses: CalculateInput,
+    pub tx_volume: CalculateInput,
+    // the asset's position among the peers of every series, if it is one of them
+    pub index: Option<usize>,
+}
+
+impl AssetSeries {
+    fn input_mut(&mut self, series: Series) -> &mut CalculateInput {
+        match series {
+            Series::Price => &mut self.price,
+            Series::Liquidity => &mut self.liquidity,
+            Series::ActiveAddresses => &mut self.active_addresses,
+            Series::TxVolume => &mut self.tx_volume,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let index = match self.index {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        for input in [
+            &self.price,
+            &self.liquidity,
+            &self.active_addresses,
+            &self.tx_volume,
+        ] {
+            if index >= input.value_all_assets.len() {
+                return Err(format!(
+                    "asset index {} is out of range for {} peers",
+                    index,
+                    input.value_all_assets.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // the asset's own entry among the peers is shocked along with it
+    fn shocked(&self, scenario: &Scenario) -> AssetSeries {
+        let mut shocked = self.clone();
+        let index = self.index;
+        for shock in &scenario.shocks {
+            let input = shocked.input_mut(shock.series());
+            let values = shock.apply(&input.values);
+            if let Some(peer) = index.and_then(|i| input.value_all_assets.get_mut(i)) {
+                *peer = values.clone();
+            }
+            input.values = values;
+        }
+        shocked
+    }
+
+    // lens scores in rating() component order
+    pub fn scores(&self) -> Vec<f64> {
+        vec![
+            metric::score::<Deviation>(&self.price).value,
+            metric::score::<Variance>(&self.price).value,
+            metric::score::<Autocorrelation>(&self.price).value,
+            metric::score::<DexLiquidity>(&self.liquidity).value,
+            metric::score::<ActiveAddress>(&self.active_addresses).value,
+            metric::score::<TxVolume>(&self.tx_volume).value,
+        ]
+    }
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct StressResult {
+    pub scenario: String,
+    pub scores: Vec<f64>,
+    pub rating: f64,
+    pub grade: Grade,
+    // stressed minus baseline rating
+    pub change: f64,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct StressReport {
+    pub baseline: StressResult,
+    pub scenarios: Vec<StressResult>,
+}
+
+pub fn stress(
+    series: &AssetSeries,
+    scenarios: &[Scenario],
+    aggregator: &Aggregator,
+) -> Result<StressReport, String> {
+    series.validate()?;
+    for shock in scenarios.iter().flat_map(|scenario| scenario.shocks.iter()) {
+        shock.validate()?;
+    }
+
+    let run = |name: &str, series: &AssetSeries| -> Result<StressResult, String> {
+        let scores = series.scores();
+        let (rating, grade) = rating_of(&scores, aggregator)?;
+        Ok(StressResult {
+            scenario: name.to_string(),
+            scores,
+            rating,
+            grade,
+            change: 0.0,
+        })
+    };
+    let baseline = run("baseline", series)?;
+    let scenarios = scenarios
+        .iter()
+        .map(|scenario| {
+            let mut result = run(&scenario.name, &series.shocked(scenario))?;
+            result.change = result.rating - baseline.rating;
+            Ok(result)
+        })
+        .collect::<Result<Vec<StressResult>, String>>()?;
+    Ok(StressReport {
+        baseline,
+        scenarios,
+    })
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize)]
+pub struct StressArgs {
+    pub price: Args,
+    pub liquidity: Args,
+    pub active_addresses: Args,
+    pub tx_volume: Args,
+    // position of the asset among `ids`, which every series lists in the same order
+    pub index: Option<usize>,
+    pub scenarios: Vec<Scenario>,
+    pub aggregator: Option<Aggregator>,
+}
+
+pub async fn calculate_stress(
+    targets: Vec<String>,
+    args: StressArgs,
+) -> Result<StressReport, String> {
+    let target = Principal::from_str(&targets[0]).map_err(|e| e.to_string())?;
+    let series = AssetSeries {
+        price: call_with_transform(target, args.price, Deviation::decode).await?,
+        liquidity: call_with_transform(target, args.liquidity, DexLiquidity::decode).await?,
+        active_addresses: call_with_transform(target, args.active_addresses, ActiveAddress::decode)
+            .await?,
+        tx_volume: call_with_transform(target, args.tx_volume, TxVolume::decode).await?,
+        index: args.index,
+    };
+    stress(
+        &series,
+        &args.scenarios,
+        &args.aggregator.unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // usdc, usdt, dai and fdusd fixtures from the component crates
+    const price: [[f64; 7]; 4] = [
+        [
+            0.999482, 1.001000, 0.999570, 1.001000, 1.001000, 0.998959, 1.000000,
+        ],
+        [
+            1.000000, 0.999738, 1.000000, 1.000000, 1.000000, 1.000000, 1.001000,
+        ],
+        [
+            0.998615, 1.000000, 1.000000, 0.999913, 1.000000, 1.002000, 1.000000,
+        ],
+        [
+            0.997341, 1.000000, 1.000000, 1.002000, 1.005000, 0.998214, 1.001000,
+        ],
+    ];
+    const liquidity: [[f64; 9]; 4] = [
+        [
+            92852142.73,
+            96521991.91,
+            104605045.15,
+            67397382.39,
+            72825403.63,
+            71642046.15,
+            65437707.89,
+            64151262.60,
+            43014497.08,
+        ],
+        [
+            2466954.95,
+            20692204.12,
+            2279293.81,
+            9899416.37,
+            1213691.66,
+            476400.51,
+            355816.98,
+            13984077.51,
+            229678.67,
+        ],
+        [
+            5558588.81, 6043658.45, 5640429.57, 3072559.7, 3295090.65, 1683993.6, 1599624.51,
+            1644650.53, 7111106.29,
+        ],
+        [
+            370916.93, 339327.94, 330683.76, 340056.62, 338320.95, 350043.94, 349716.82, 370261.87,
+            367124.08,
+        ],
+    ];
+    const addresses: [[f64; 6]; 4] = [
+        [20756.0, 39127.0, 20996.0, 20644.0, 21952.0, 24694.0],
+        [55211.0, 96979.0, 50291.0, 51362.0, 49945.0, 51539.0],
+        [1615.0, 2625.0, 1476.0, 1849.0, 2057.0, 2399.0],
+        [26.0, 48.0, 31.0, 76.0, 31.0, 22.0],
+    ];
+    const volume: [[f64; 6]; 4] = [
+        [
+            11297494841.0,
+            16662147803.0,
+            7903871311.0,
+            14753101769.0,
+            7527660368.0,
+            9817033672.0,
+        ],
+        [
+            10595078509.0,
+            12406512875.0,
+            5989646841.0,
+            9102603766.0,
+            5563919565.0,
+            9521087992.0,
+        ],
+        [
+            11869832525.0,
+            8445518282.0,
+            4672267966.0,
+            7374460791.0,
+            10147684330.0,
+            19339245814.0,
+        ],
+        [
+            919638661.0,
+            469195441.0,
+            210240247.0,
+            2153513563.0,
+            386958583.0,
+            544515.0,
+        ],
+    ];
+
+    fn input<const N: usize>(fixtures: &[[f64; N]; 4], asset: usize) -> CalculateInput {
+        CalculateInput {
+            values: fixtures[asset].to_vec(),
+            value_all_assets: fixtures.iter().map(|data| data.to_vec()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn series(asset: usize) -> AssetSeries {
+        AssetSeries {
+            price: input(&price, asset),
+            liquidity: input(&liquidity, asset),
+            active_addresses: input(&addresses, asset),
+            tx_volume: input(&volume, asset),
+            index: Some(asset),
+        }
+    }
+
+    fn scenario(name: &str, shocks: Vec<Shock>) -> Scenario {
+        Scenario {
+            name: name.to_string(),
+            shocks,
+        }
+    }
+
+    #[test]
+    fn test_apply_shocks() {
+        let data = [1.0, 1.0, 1.0, 1.0];
+        let level = Shock::Level {
+            series: Series::Price,
+            level: 0.95,
+            days: 2,
+        };
+        assert_eq!(level.apply(&data), vec![1.0, 1.0, 0.95, 0.95]);
+        let scale = Shock::Scale {
+            series: Series::Liquidity,
+            factor: 0.2,
+            days: None,
+        };
+        assert_eq!(scale.apply(&data), vec![0.2; 4]);
+        let path = Shock::Path {
+            series: Series::TxVolume,
+            factors: vec![2.0, 3.0],
+        };
+        assert_eq!(path.apply(&data), vec![1.0, 1.0, 2.0, 3.0]);
+        // a path longer than the series is cut at the series length
+        assert_eq!(path.apply(&[1.0]), vec![2.0]);
+    }
+
+    #[test]
+    fn test_baseline_matches_rating() {
+        let series = series(0);
+        let report = stress(&series, &[], &Aggregator::Geometric).unwrap();
+        let (expected, _) = rating_of(&series.scores(), &Aggregator::Geometric).unwrap();
+        assert_eq!(report.baseline.rating, expected);
+        assert_eq!(report.baseline.change, 0.0);
+        assert!(report.scenarios.is_empty());
+    }
+
+    #[test]
+    fn test_depeg() {
+        let depeg = scenario(
+            "depeg",
+            vec![Shock::Level {
+                series: Series::Price,
+                level: 0.95,
+                days: 3,
+            }],
+        );
+        let report = stress(&series(0), &[depeg], &Aggregator::Geometric).unwrap();
+        let result = &report.scenarios[0];
+        assert_eq!(result.scenario, "depeg");
+        assert!(result.scores[0] < report.baseline.scores[0]);
+        assert!(result.change < 0.0);
+        // the non-price components are untouched
+        assert_eq!(result.scores[3..], report.baseline.scores[3..]);
+    }
+
+    #[test]
+    fn test_liquidity_and_address_shocks() {
+        let scenarios = [
+            scenario(
+                "liquidity",
+                vec![Shock::Scale {
+                    series: Series::Liquidity,
+                    factor: 0.2,
+                    days: None,
+                }],
+            ),
+            scenario(
+                "addresses",
+                vec![Shock::Scale {
+                    series: Series::ActiveAddresses,
+                    factor: 0.5,
+                    days: None,
+                }],
+            ),
+        ];
+        // usdt holds the most active addresses of the four
+        let report = stress(&series(1), &scenarios, &Aggregator::Geometric).unwrap();
+        assert!(report.scenarios[0].scores[3] < report.baseline.scores[3]);
+        assert!(report.scenarios[0].change < 0.0);
+        assert_eq!(report.scenarios[1].scores[4], report.baseline.scores[4]);
+
+        let report = stress(&series(0), &scenarios, &Aggregator::Geometric).unwrap();
+        assert!(report.scenarios[1].scores[4] < report.baseline.scores[4]);
+    }
+
+    #[test]
+    fn test_invalid_shock() {
+        let invalid = scenario(
+            "invalid",
+            vec![Shock::Scale {
+                series: Series::TxVolume,
+                factor: -1.0,
+                days: None,
+            }],
+        );
+        assert!(stress(&series(0), &[invalid], &Aggregator::Geometric).is_err());
+    }
+
+    #[test]
+    fn test_only_own_peer_is_shocked() {
+        let depeg = scenario(
+            "depeg",
+            vec![Shock::Level {
+                series: Series::Price,
+                level: 0.95,
+                days: 3,
+            }],
+        );
+        // a peer that happens to share the asset's prices is left alone
+        let mut twin = series(0);
+        twin.price.value_all_assets.push(price[0].to_vec());
+        let shocked = twin.shocked(&depeg);
+        assert_eq!(shocked.price.value_all_assets[0], shocked.price.values);
+        assert_eq!(shocked.price.value_all_assets[1..4], price[1..]);
+        assert_eq!(shocked.price.value_all_assets[4], price[0]);
+
+        let unlisted = AssetSeries {
+            index: None,
+            ..series(0)
+        };
+        let shocked = unlisted.shocked(&depeg);
+        assert_eq!(shocked.price.value_all_assets[0], price[0].to_vec());
+    }
+
+    #[test]
+    fn test_index_out_of_range() {
+        let series = AssetSeries {
+            index: Some(price.len()),
+            ..series(0)
+        };
+        assert!(stress(&series, &[], &Aggregator::Geometric).is_err());
+    }
+}
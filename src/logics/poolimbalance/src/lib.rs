@@ -0,0 +1,271 @@
+use std::str::FromStr;
+
+use candid::Principal;
+use common::{
+    bootstrap::Interval,
+    call_with_transform,
+    guard::Outcome,
+    metric::{self, Direction, Metric},
+    undecodable,
+    weighting::{weighted_mean, Weighting},
+    Args, CalculateInput,
+};
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct CalculateArgs {
+    pub args: Args,
+    // position of the rated asset among the balances, the same in every pool
+    pub index: u32,
+}
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct LensValue {
+    pub value: f64,
+    pub outcome: Outcome,
+    pub statistic: f64,
+    pub interval: Option<Interval>,
+}
+impl From<CalculateInput> for LensValue {
+    fn from(input: CalculateInput) -> Self {
+        let score = metric::score::<PoolImbalance>(&input);
+        LensValue {
+            value: score.value,
+            outcome: score.outcome,
+            statistic: metric::statistic::<PoolImbalance>(&input),
+            interval: input
+                .bootstrap
+                .as_ref()
+                .map(|bootstrap| metric::interval::<PoolImbalance>(&input, bootstrap)),
+        }
+    }
+}
+
+pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> Result<LensValue, String> {
+    let target = Principal::from_str(&targets[0]).map_err(|e| e.to_string())?;
+    let index = args.index as usize;
+
+    // each snapshot holds the pool balances
+    let input = call_with_transform(target, args.args, |f| {
+        let balances = f.values().ok_or_else(|| undecodable(&f))?;
+        relative_share(&balances, index)
+    })
+    .await?;
+    Ok(LensValue::from(input))
+}
+
+// pool shares are not resolved below one basis point, so a perfectly balanced pool
+// scores as a one-basis-point imbalance instead of falling off the scale
+const IMBALANCE_RESOLUTION: f64 = 1e-4;
+
+// the asset's share of the pool relative to a balanced share, 1.0 when balanced
+fn relative_share(balances: &[f64], index: usize) -> Result<f64, String> {
+    if index >= balances.len() {
+        return Err(format!(
+            "asset index {} is out of range for a pool of {}",
+            index,
+            balances.len()
+        ));
+    }
+    let total: f64 = balances.iter().sum();
+    if !total.is_finite() || total <= 0.0 {
+        return Err(format!(
+            "pool balances must have a positive total, got {}",
+            total
+        ));
+    }
+
+    Ok(balances[index] / total * balances.len() as f64)
+}
+
+fn average_imbalance(data: &[f64], weighting: &Weighting) -> f64 {
+    let n = data.len() as f64;
+    if n == 0.0 {
+        return 0.0;
+    }
+
+    let imbalance = data.iter().map(|&x| (x - 1.0).abs()).collect::<Vec<f64>>();
+    weighted_mean(&imbalance, weighting)
+}
+
+pub struct PoolImbalance;
+
+impl Metric for PoolImbalance {
+    const NAME: &'static str = "poolimbalance";
+    const DIRECTION: Direction = Direction::LowerIsBetter;
+    const RESOLUTION: f64 = IMBALANCE_RESOLUTION;
+
+    fn statistic(data: &[f64], weighting: &Weighting) -> f64 {
+        average_imbalance(data, weighting)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const usdc: [[f64; 3]; 6] = [
+        [182.4e6, 171.9e6, 395.2e6],
+        [180.1e6, 173.5e6, 396.0e6],
+        [176.3e6, 175.2e6, 398.1e6],
+        [150.7e6, 180.4e6, 419.0e6],
+        [162.9e6, 178.8e6, 407.6e6],
+        [171.2e6, 176.0e6, 401.3e6],
+    ];
+    const usdt: [[f64; 3]; 6] = [
+        [395.2e6, 182.4e6, 171.9e6],
+        [396.0e6, 180.1e6, 173.5e6],
+        [398.1e6, 176.3e6, 175.2e6],
+        [419.0e6, 150.7e6, 180.4e6],
+        [407.6e6, 162.9e6, 178.8e6],
+        [401.3e6, 171.2e6, 176.0e6],
+    ];
+    const dai: [[f64; 3]; 6] = [
+        [171.9e6, 182.4e6, 395.2e6],
+        [173.5e6, 180.1e6, 396.0e6],
+        [175.2e6, 176.3e6, 398.1e6],
+        [180.4e6, 150.7e6, 419.0e6],
+        [178.8e6, 162.9e6, 407.6e6],
+        [176.0e6, 171.2e6, 401.3e6],
+    ];
+    const fdusd: [[f64; 2]; 6] = [
+        [8.21e6, 7.94e6],
+        [8.63e6, 7.52e6],
+        [9.87e6, 6.31e6],
+        [11.42e6, 4.96e6],
+        [10.05e6, 6.12e6],
+        [9.31e6, 6.88e6],
+    ];
+
+    fn series<const N: usize>(pool: &[[f64; N]]) -> Vec<f64> {
+        pool.iter()
+            .map(|balances| relative_share(balances, 0).unwrap())
+            .collect()
+    }
+
+    fn input(data: Vec<f64>) -> CalculateInput {
+        CalculateInput {
+            values: data,
+            value_all_assets: vec![series(&usdc), series(&usdt), series(&dai), series(&fdusd)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_balanced_pool() {
+        let data = [1.0, 1.0, 1.0];
+        let expected = 1.0;
+        let result = relative_share(&data, 0).unwrap();
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_invalid_pool() {
+        assert!(relative_share(&[], 0).is_err());
+        assert!(relative_share(&[1.0, 1.0], 2).is_err());
+        assert!(relative_share(&[0.0, 0.0], 0).is_err());
+        assert!(relative_share(&[1.0, f64::NAN], 0).is_err());
+    }
+
+    #[test]
+    fn test_index_picks_the_rated_asset() {
+        let data = [1.0, 3.0];
+        let expected = 1.5;
+        let result = relative_share(&data, 1).unwrap();
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_empty_slice() {
+        let data = [];
+        let expected = 0.0;
+        let result = average_imbalance(&data, &Weighting::Uniform);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_balanced_pool_log10() {
+        let data = [1.0; 6];
+        let expected = 4.0;
+        let result = PoolImbalance::log10(&data, &Weighting::Uniform);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_balanced_pool_scores_top() {
+        let balanced = vec![1.0; 6];
+        let mut input = input(balanced.clone());
+        input.value_all_assets.push(balanced);
+        let expected = 1.0;
+        let result = LensValue::from(input).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_6days() {
+        let data = series(&usdc);
+        let expected = 0.3170753065965493;
+        let result = average_imbalance(&data, &Weighting::Uniform);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdt_6days() {
+        let data = series(&usdt);
+        let expected = 0.6126776797916487;
+        let result = average_imbalance(&data, &Weighting::Uniform);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_usdc() {
+        let expected = 0.6745294923701783;
+        let result = LensValue::from(input(series(&usdc))).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_usdt() {
+        let expected = 0.2877053720193575;
+        let result = LensValue::from(input(series(&usdt))).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_dai() {
+        let expected = 0.7157101540561777;
+        let result = LensValue::from(input(series(&dai))).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_fdusd() {
+        let expected = 1.0;
+        let result = LensValue::from(input(series(&fdusd))).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_weighting_reaches_statistic() {
+        let weighted = CalculateInput {
+            weighting: Weighting::HalfLife(1.0),
+            ..input(series(&usdc))
+        };
+        let uniform = LensValue::from(input(series(&usdc))).statistic;
+        assert!(LensValue::from(weighted).statistic != uniform);
+    }
+
+    #[test]
+    fn test_score_interval_usdc() {
+        let input = CalculateInput {
+            bootstrap: Some(common::bootstrap::Bootstrap::default()),
+            ..input(series(&usdc))
+        };
+        let result = LensValue::from(input);
+        let interval = result.interval.unwrap();
+        assert!(interval.lower <= result.value && result.value <= interval.upper);
+        assert!(interval.width() > 0.0, "got {:?}", interval);
+    }
+
+    #[test]
+    fn test_no_interval_without_bootstrap() {
+        assert_eq!(LensValue::from(input(series(&usdc))).interval, None);
+    }
+}
@@ -0,0 +1,176 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::weights::Weights;
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, Default, PartialEq)]
+pub enum Aggregator {
+    #[default]
+    Geometric,
+    Arithmetic,
+    Harmonic,
+    // the rating is only as strong as its weakest component
+    WeakestLink,
+    // arithmetic mean capped at `cap` whenever any component falls below `floor`
+    Penalized {
+        floor: f64,
+        cap: f64,
+    },
+}
+
+impl Aggregator {
+    pub fn aggregate(&self, scores: &[f64], weights: &Weights) -> f64 {
+        match self {
+            Aggregator::Geometric => weighted_geometric_mean(scores, weights),
+            Aggregator::Arithmetic => weighted_arithmetic_mean(scores, weights),
+            Aggregator::Harmonic => weighted_harmonic_mean(scores, weights),
+            Aggregator::WeakestLink => weakest_link(scores, weights),
+            Aggregator::Penalized { floor, cap } => penalized_mean(scores, weights, *floor, *cap),
+        }
+    }
+}
+
+pub fn weighted_geometric_mean(scores: &[f64], weights: &Weights) -> f64 {
+    scores
+        .iter()
+        .zip(weights.0.iter())
+        .map(|(score, weight)| score.powf(*weight))
+        .fold(1.0, |acc, score| acc * score)
+}
+
+fn weighted_arithmetic_mean(scores: &[f64], weights: &Weights) -> f64 {
+    scores
+        .iter()
+        .zip(weights.0.iter())
+        .map(|(score, weight)| score * weight)
+        .sum()
+}
+
+fn weighted_harmonic_mean(scores: &[f64], weights: &Weights) -> f64 {
+    let mut denominator = 0.0;
+    for (score, weight) in scores.iter().zip(weights.0.iter()) {
+        if *weight == 0.0 {
+            continue;
+        }
+        if *score == 0.0 {
+            return 0.0;
+        }
+        denominator += weight / score;
+    }
+
+    if denominator == 0.0 {
+        0.0
+    } else {
+        1.0 / denominator
+    }
+}
+
+// components with zero weight are switched off and cannot be the weakest link
+fn weakest_link(scores: &[f64], weights: &Weights) -> f64 {
+    let weakest = scores
+        .iter()
+        .zip(weights.0.iter())
+        .filter(|(_, weight)| **weight > 0.0)
+        .map(|(score, _)| *score)
+        .fold(f64::INFINITY, f64::min);
+
+    if weakest.is_finite() {
+        weakest
+    } else {
+        0.0
+    }
+}
+
+fn penalized_mean(scores: &[f64], weights: &Weights, floor: f64, cap: f64) -> f64 {
+    let mean = weighted_arithmetic_mean(scores, weights);
+    let breached = scores
+        .iter()
+        .zip(weights.0.iter())
+        .any(|(score, weight)| *weight > 0.0 && *score < floor);
+
+    if breached {
+        mean.min(cap)
+    } else {
+        mean
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const usdc: [f64; 6] = [4.202794, 1.921468, 4.528004, 5.000000, 4.602019, 5.000000];
+    const usdt: [f64; 6] = [5.000000, 5.000000, 5.000000, 4.289800, 5.000000, 4.947025];
+
+    #[test]
+    fn test_all_elements_same() {
+        let scores = [3.0, 3.0, 3.0, 3.0, 3.0, 3.0];
+        let weights = Weights::equal(6);
+        for aggregator in [Aggregator::Arithmetic, Aggregator::WeakestLink] {
+            let expected = 3.0;
+            let result = aggregator.aggregate(&scores, &weights);
+            assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+        }
+    }
+
+    #[test]
+    fn test_usdc_arithmetic() {
+        let expected = 4.2090475;
+        let result = Aggregator::Arithmetic.aggregate(&usdc, &Weights::equal(6));
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_harmonic() {
+        let expected = 3.7581830625660535;
+        let result = Aggregator::Harmonic.aggregate(&usdc, &Weights::equal(6));
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_weakest_link() {
+        let expected = 1.921468;
+        let result = Aggregator::WeakestLink.aggregate(&usdc, &Weights::equal(6));
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_penalized() {
+        let aggregator = Aggregator::Penalized {
+            floor: 2.0,
+            cap: 3.0,
+        };
+        let expected = 3.0;
+        let result = aggregator.aggregate(&usdc, &Weights::equal(6));
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdt_penalized_not_breached() {
+        let aggregator = Aggregator::Penalized {
+            floor: 2.0,
+            cap: 3.0,
+        };
+        let expected = Aggregator::Arithmetic.aggregate(&usdt, &Weights::equal(6));
+        let result = aggregator.aggregate(&usdt, &Weights::equal(6));
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_ordering() {
+        let weights = Weights::equal(6);
+        let arithmetic = Aggregator::Arithmetic.aggregate(&usdc, &weights);
+        let geometric = Aggregator::Geometric.aggregate(&usdc, &weights);
+        let harmonic = Aggregator::Harmonic.aggregate(&usdc, &weights);
+        let weakest = Aggregator::WeakestLink.aggregate(&usdc, &weights);
+        assert!(arithmetic >= geometric && geometric >= harmonic && harmonic >= weakest);
+    }
+
+    #[test]
+    fn test_zero_component() {
+        let scores = [0.0, 5.0, 5.0, 5.0, 5.0, 5.0];
+        let weights = Weights::equal(6);
+        assert_eq!(Aggregator::Harmonic.aggregate(&scores, &weights), 0.0);
+        assert_eq!(Aggregator::WeakestLink.aggregate(&scores, &weights), 0.0);
+    }
+}
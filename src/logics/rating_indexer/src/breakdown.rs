@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aggregate::Aggregator,
+    state::{config, update_config},
+    weights::Weights,
+};
+
+// rating() component order, with the optional supply component last
+pub const COMPONENTS: [&str; 7] = [
+    "deviation",
+    "variance",
+    "autocorrelation",
+    "dexliquidity",
+    "activeaddress",
+    "txvolume",
+    "supplydynamics",
+];
+
+// lens score below which a component is flagged when no threshold is configured
+const DEFAULT_THRESHOLD: f64 = 0.5;
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct ComponentBreakdown {
+    pub name: String,
+    pub statistic: Option<f64>,
+    pub score: f64,
+    pub rating: f64,
+    pub weight: f64,
+    pub contribution: f64,
+}
+
+pub fn reason_code(component: &str) -> &'static str {
+    match component {
+        "deviation" => "HIGH_DEVIATION",
+        "variance" => "HIGH_VARIANCE",
+        "autocorrelation" => "HIGH_AUTOCORRELATION",
+        "dexliquidity" => "LOW_LIQUIDITY",
+        "activeaddress" => "LOW_ACTIVE_ADDRESSES",
+        "txvolume" => "LOW_TX_VOLUME",
+        "supplydynamics" => "UNSTABLE_SUPPLY",
+        _ => "LOW_SCORE",
+    }
+}
+
+pub fn set_reason_threshold(component: String, threshold: f64) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(format!("threshold must be in [0, 1], got {}", threshold));
+    }
+    update_config(|c| {
+        c.reason_thresholds
+            .get_or_insert_with(Default::default)
+            .insert(component, threshold);
+    })
+}
+
+fn reason_threshold(thresholds: &BTreeMap<String, f64>, component: &str) -> f64 {
+    thresholds
+        .get(component)
+        .cloned()
+        .unwrap_or(DEFAULT_THRESHOLD)
+}
+
+// contributions add up to the rating: log shares for the geometric mean, the whole
+// rating on the weakest component for weakest-link, weighted points otherwise. The log
+// shares use ln(1 + r) so a rating below 1 cannot flip a share's sign, nor can the
+// total approach zero while individual shares stay large
+fn contributions(
+    aggregator: &Aggregator,
+    ratings: &[f64],
+    weights: &Weights,
+    rating: f64,
+) -> Vec<f64> {
+    let parts = match aggregator {
+        Aggregator::Geometric => ratings
+            .iter()
+            .zip(weights.0.iter())
+            .map(|(r, w)| w * r.ln_1p())
+            .collect::<Vec<f64>>(),
+        Aggregator::WeakestLink => {
+            let weakest = ratings
+                .iter()
+                .zip(weights.0.iter())
+                .filter(|(_, w)| **w > 0.0)
+                .map(|(r, _)| *r)
+                .fold(f64::INFINITY, f64::min);
+            // a weight-0 component cannot bind, even when it ties the weakest rating
+            ratings
+                .iter()
+                .zip(weights.0.iter())
+                .map(|(r, w)| if *w > 0.0 && *r == weakest { 1.0 } else { 0.0 })
+                .collect()
+        }
+        _ => ratings
+            .iter()
+            .zip(weights.0.iter())
+            .map(|(r, w)| w * r)
+            .collect(),
+    };
+    let total: f64 = parts.iter().sum();
+    if total == 0.0 || !total.is_finite() {
+        return weights.0.iter().map(|w| w * rating).collect();
+    }
+
+    parts.iter().map(|part| part / total * rating).collect()
+}
+
+pub fn breakdown(
+    scores: &[f64],
+    statistics: &[f64],
+    ratings: &[f64],
+    weights: &Weights,
+    aggregator: &Aggregator,
+    rating: f64,
+) -> (Vec<ComponentBreakdown>, Vec<String>) {
+    let contributions = contributions(aggregator, ratings, weights, rating);
+    let thresholds = config().reason_thresholds.unwrap_or_default();
+    let mut components = vec![];
+    let mut reasons = vec![];
+    for (i, score) in scores.iter().enumerate() {
+        let name = COMPONENTS.get(i).cloned().unwrap_or("unknown");
+        if *score < reason_threshold(&thresholds, name) {
+            reasons.push(reason_code(name).to_string());
+        }
+        components.push(ComponentBreakdown {
+            name: name.to_string(),
+            statistic: statistics.get(i).cloned(),
+            score: *score,
+            rating: ratings[i],
+            weight: weights.0[i],
+            contribution: contributions[i],
+        });
+    }
+    (components, reasons)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+
+    const usdc: [f64; 6] = [
+        0.8405587657505329,
+        0.9056007028336818,
+        0.38429367814737314,
+        1.0,
+        0.9204038358579446,
+        1.0,
+    ];
+    const usdc_rating: f64 = 4.01785648279077;
+
+    fn ratings(scores: &[f64]) -> Vec<f64> {
+        scores.iter().map(|score| score * 5.0).collect()
+    }
+
+    #[test]
+    fn test_geometric_contributions_sum_to_rating() {
+        let result = contributions(
+            &Aggregator::Geometric,
+            &ratings(&usdc),
+            &Weights::equal(6),
+            usdc_rating,
+        );
+        let total: f64 = result.iter().sum();
+        assert!((total - usdc_rating).abs() < 1e-12);
+        // the weak autocorrelation component contributes the least
+        assert!(result.iter().all(|c| *c >= result[2]));
+    }
+
+    #[test]
+    fn test_weakest_link_contribution() {
+        let ratings = ratings(&usdc);
+        let rating = ratings[2];
+        let result = contributions(
+            &Aggregator::WeakestLink,
+            &ratings,
+            &Weights::equal(6),
+            rating,
+        );
+        assert_eq!(result, vec![0.0, 0.0, rating, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_weakest_link_skips_unweighted_ties() {
+        let ratings = [2.0, 4.0, 2.0, 5.0, 5.0, 5.0];
+        let weights = Weights(vec![0.2, 0.2, 0.0, 0.2, 0.2, 0.2]);
+        let result = contributions(&Aggregator::WeakestLink, &ratings, &weights, 2.0);
+        assert_eq!(result, vec![2.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_unit_ratings_fall_back_to_weights() {
+        let result = contributions(&Aggregator::Geometric, &[1.0; 6], &Weights::equal(6), 1.0);
+        assert_eq!(result, Weights::equal(6).0);
+    }
+
+    #[test]
+    fn test_geometric_contributions_keep_their_sign() {
+        // ln of these ratings nearly cancels out
+        let ratings = [0.5, 2.0, 1.0, 1.0, 1.0, 1.0];
+        let weights = Weights::equal(6);
+        let rating = Aggregator::Geometric.aggregate(&ratings, &weights);
+        let result = contributions(&Aggregator::Geometric, &ratings, &weights, rating);
+        let total: f64 = result.iter().sum();
+        assert!((total - rating).abs() < 1e-12);
+        assert!(result.iter().all(|c| *c >= 0.0 && *c <= rating));
+        assert!(result[0] < result[2] && result[2] < result[1]);
+    }
+
+    #[test]
+    fn test_usdc_reasons() {
+        let (components, reasons) = breakdown(
+            &usdc,
+            &[],
+            &ratings(&usdc),
+            &Weights::equal(6),
+            &Aggregator::Geometric,
+            usdc_rating,
+        );
+        assert_eq!(reasons, vec!["HIGH_AUTOCORRELATION"]);
+        assert_eq!(components[3].name, "dexliquidity");
+        assert_eq!(components[3].statistic, None);
+    }
+
+    #[test]
+    fn test_configured_threshold() {
+        state::tests::init();
+        set_reason_threshold("activeaddress".to_string(), 0.95).unwrap();
+        let (_, reasons) = breakdown(
+            &usdc,
+            &[],
+            &ratings(&usdc),
+            &Weights::equal(6),
+            &Aggregator::Geometric,
+            usdc_rating,
+        );
+        assert_eq!(
+            reasons,
+            vec!["HIGH_AUTOCORRELATION", "LOW_ACTIVE_ADDRESSES"]
+        );
+        assert!(set_reason_threshold("activeaddress".to_string(), 1.5).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use common::bootstrap::Interval;
+
+use crate::{
+    aggregate::Aggregator,
+    scale::ScaleConversion,
+    state::{config, update_config},
+    weights::Weights,
+};
+
+// rating points an interval may span before the rating is flagged as low confidence
+const DEFAULT_MAX_WIDTH: f64 = 0.5;
+
+// every aggregator is non-decreasing in each component, so aggregating the component
+// bounds gives a conservative interval for the rating
+pub fn rating_interval(
+    intervals: &[Interval],
+    conversion: &ScaleConversion,
+    aggregator: &Aggregator,
+    weights: &Weights,
+) -> Interval {
+    let bound = |f: fn(&Interval) -> f64| {
+        let scores = intervals
+            .iter()
+            .map(|interval| conversion.to_rating(f(interval)))
+            .collect::<Vec<f64>>();
+        aggregator.aggregate(&scores, weights)
+    };
+    Interval {
+        lower: bound(|interval| interval.lower),
+        upper: bound(|interval| interval.upper),
+    }
+}
+
+pub fn set_max_interval_width(width: f64) -> Result<(), String> {
+    if !width.is_finite() || width <= 0.0 {
+        return Err(format!(
+            "max interval width must be positive, got {}",
+            width
+        ));
+    }
+    update_config(|c| c.max_interval_width = Some(width))
+}
+
+pub fn low_confidence(interval: &Interval) -> bool {
+    interval.width() > config().max_interval_width.unwrap_or(DEFAULT_MAX_WIDTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+
+    fn interval(lower: f64, upper: f64) -> Interval {
+        Interval { lower, upper }
+    }
+
+    #[test]
+    fn test_rating_interval_brackets_rating() {
+        let intervals = vec![
+            interval(0.8, 0.9),
+            interval(0.85, 0.95),
+            interval(0.3, 0.45),
+            interval(1.0, 1.0),
+            interval(0.85, 0.95),
+            interval(0.9, 1.0),
+        ];
+        let weights = Weights::equal(6);
+        let result = rating_interval(
+            &intervals,
+            &ScaleConversion::Linear,
+            &Aggregator::Geometric,
+            &weights,
+        );
+        let rating = Aggregator::Geometric.aggregate(&[4.25, 4.5, 1.9, 5.0, 4.5, 4.75], &weights);
+        assert!(result.lower < rating && rating < result.upper);
+    }
+
+    #[test]
+    fn test_point_intervals() {
+        let intervals = vec![interval(0.8, 0.8); 6];
+        let result = rating_interval(
+            &intervals,
+            &ScaleConversion::Linear,
+            &Aggregator::Geometric,
+            &Weights::equal(6),
+        );
+        assert!(result.width().abs() < 1e-12);
+        assert!(!low_confidence(&result));
+    }
+
+    #[test]
+    fn test_low_confidence() {
+        state::tests::init();
+        assert!(low_confidence(&interval(3.0, 4.0)));
+        assert!(!low_confidence(&interval(3.8, 4.1)));
+        assert!(set_max_interval_width(0.0).is_err());
+        set_max_interval_width(1.5).unwrap();
+        assert!(!low_confidence(&interval(3.0, 4.0)));
+    }
+}
@@ -0,0 +1,231 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::state::{config, update_config};
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Cutoff {
+    pub min: f64,
+    pub grade: String,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct GradeScale {
+    pub version: u32,
+    // highest grade first
+    pub cutoffs: Vec<Cutoff>,
+    pub notches: bool,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Grade {
+    pub grade: String,
+    pub version: u32,
+}
+
+impl Default for GradeScale {
+    // version 1 of the 0-5 scale agreed for the stablecoin ratings
+    fn default() -> Self {
+        let cutoffs = [
+            (4.75, "AAA"),
+            (4.25, "AA"),
+            (3.75, "A"),
+            (3.25, "BBB"),
+            (2.5, "BB"),
+            (1.75, "B"),
+            (1.0, "CCC"),
+            (0.5, "CC"),
+            (0.25, "C"),
+            (0.0, "D"),
+        ];
+        GradeScale {
+            version: 1,
+            cutoffs: cutoffs
+                .iter()
+                .map(|(min, grade)| Cutoff {
+                    min: *min,
+                    grade: grade.to_string(),
+                })
+                .collect(),
+            notches: true,
+        }
+    }
+}
+
+impl GradeScale {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.cutoffs.is_empty() {
+            return Err("grade scale needs at least one cutoff".to_string());
+        }
+        if self.cutoffs.windows(2).any(|w| w[0].min <= w[1].min) {
+            return Err("cutoffs must be strictly descending".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn grade(&self, rating: f64) -> Grade {
+        let index = self
+            .cutoffs
+            .iter()
+            .position(|cutoff| rating >= cutoff.min)
+            .unwrap_or(self.cutoffs.len() - 1);
+        let cutoff = &self.cutoffs[index];
+
+        // the top and bottom grades are open-ended, so only inner bands are notched
+        let notch = if self.notches && index > 0 && index < self.cutoffs.len() - 1 {
+            let upper = self.cutoffs[index - 1].min;
+            let position = (rating - cutoff.min) / (upper - cutoff.min);
+            if position >= 2.0 / 3.0 {
+                "+"
+            } else if position < 1.0 / 3.0 {
+                "-"
+            } else {
+                ""
+            }
+        } else {
+            ""
+        };
+        Grade {
+            grade: format!("{}{}", cutoff.grade, notch),
+            version: self.version,
+        }
+    }
+
+    // every grade grade() can return with the lowest rating that earns it, highest first
+    pub fn bands(&self) -> Vec<Cutoff> {
+        let last = self.cutoffs.len() - 1;
+        let mut bands = vec![];
+        for (index, cutoff) in self.cutoffs.iter().enumerate() {
+            if !self.notches || index == 0 || index == last {
+                bands.push(cutoff.clone());
+                continue;
+            }
+            let span = self.cutoffs[index - 1].min - cutoff.min;
+            for (notch, position) in [("+", 2.0 / 3.0), ("", 1.0 / 3.0), ("-", 0.0)] {
+                bands.push(Cutoff {
+                    min: cutoff.min + position * span,
+                    grade: format!("{}{}", cutoff.grade, notch),
+                });
+            }
+        }
+        bands
+    }
+}
+
+pub fn set_grade_scale(scale: GradeScale) -> Result<(), String> {
+    scale.validate()?;
+    update_config(|c| c.grade_scale = Some(scale))
+}
+
+pub fn grade_scale() -> GradeScale {
+    config().grade_scale.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+
+    fn grade(rating: f64) -> String {
+        GradeScale::default().grade(rating).grade
+    }
+
+    #[test]
+    fn test_default_scale_is_valid() {
+        assert!(GradeScale::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_usdc_grade() {
+        let expected = "A";
+        let result = grade(4.017856419662701);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdt_grade() {
+        let expected = "AAA";
+        let result = grade(4.8653063858730174);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_dai_grade() {
+        let expected = "AA-";
+        let result = grade(4.3798898528148325);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_fdusd_grade() {
+        let expected = "BBB";
+        let result = grade(3.4510228498125803);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_notches() {
+        assert_eq!(grade(4.7), "AA+");
+        assert_eq!(grade(3.8), "A-");
+        assert_eq!(grade(0.0), "D");
+        assert_eq!(grade(5.0), "AAA");
+    }
+
+    #[test]
+    fn test_bands() {
+        let bands = GradeScale::default().bands();
+        assert_eq!(bands.len(), 26);
+        assert_eq!(bands[0].grade, "AAA");
+        assert_eq!(bands[1].grade, "AA+");
+        assert_eq!(bands[25].grade, "D");
+        assert!(bands.windows(2).all(|w| w[0].min > w[1].min));
+        for band in &bands {
+            // nudged above the boundary to stay clear of rounding at the exact cut-off
+            assert_eq!(grade(band.min + 1e-9), band.grade);
+        }
+    }
+
+    #[test]
+    fn test_without_notches() {
+        let scale = GradeScale {
+            notches: false,
+            ..Default::default()
+        };
+        assert_eq!(scale.grade(4.7).grade, "AA");
+    }
+
+    #[test]
+    fn test_custom_scale() {
+        state::tests::init();
+        let scale = GradeScale {
+            version: 2,
+            cutoffs: vec![
+                Cutoff {
+                    min: 4.0,
+                    grade: "Investment".to_string(),
+                },
+                Cutoff {
+                    min: 0.0,
+                    grade: "Speculative".to_string(),
+                },
+            ],
+            notches: true,
+        };
+        set_grade_scale(scale).unwrap();
+        let expected = Grade {
+            grade: "Investment".to_string(),
+            version: 2,
+        };
+        let result = grade_scale().grade(4.017856419662701);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_unordered_cutoffs_are_rejected() {
+        state::tests::init();
+        let mut scale = GradeScale::default();
+        scale.cutoffs.swap(0, 1);
+        assert!(set_grade_scale(scale).is_err());
+        assert_eq!(grade_scale(), GradeScale::default());
+    }
+}
@@ -0,0 +1,242 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Encode};
+use ic_stable_structures::{
+    memory_manager::VirtualMemory, storable::Blob, BoundedStorable, DefaultMemoryImpl,
+    StableBTreeMap, Storable,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{aggregate::Aggregator, scale::ScaleConversion, weights::Weights};
+
+pub type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+const MAX_ASSET_ID_SIZE: usize = 64;
+const MAX_RECORD_SIZE: u32 = 1024;
+
+type AssetId = Blob<MAX_ASSET_ID_SIZE>;
+type HistoryKey = (AssetId, u64);
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct RatingRecord {
+    pub timestamp: u64,
+    pub rating: f64,
+    pub smoothed_rating: f64,
+    // published grade, which may lag the smoothed rating under hysteresis
+    pub grade: String,
+    // consecutive runs whose smoothed rating pointed at a different grade
+    pub pending: u32,
+    pub scores: Vec<f64>,
+    // how the scores were combined, unset on records written before it was stored
+    pub aggregator: Option<Aggregator>,
+    // the weights and scale conversion in force when the rating was computed, likewise
+    // unset on older records
+    pub weights: Option<Weights>,
+    pub scale_conversion: Option<ScaleConversion>,
+}
+
+impl Storable for RatingRecord {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl BoundedStorable for RatingRecord {
+    const MAX_SIZE: u32 = MAX_RECORD_SIZE;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+// same shape as the bulk snapshot indexer's query_between options
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize)]
+pub struct QueryOptions {
+    pub from_timestamp: Option<i64>,
+    pub to_timestamp: Option<i64>,
+}
+
+thread_local! {
+    static HISTORY: RefCell<Option<StableBTreeMap<HistoryKey, RatingRecord, Memory>>> =
+        const { RefCell::new(None) };
+}
+
+// the canister owns the memory manager and hands over a dedicated virtual memory
+pub fn init_history(memory: Memory) {
+    HISTORY.with(|h| *h.borrow_mut() = Some(StableBTreeMap::init(memory)));
+}
+
+fn asset_id(asset: &str) -> Result<AssetId, String> {
+    AssetId::try_from(asset.as_bytes())
+        .map_err(|_| format!("asset id {} exceeds {} bytes", asset, MAX_ASSET_ID_SIZE))
+}
+
+fn with_history<T>(
+    f: impl FnOnce(&mut StableBTreeMap<HistoryKey, RatingRecord, Memory>) -> T,
+) -> Result<T, String> {
+    HISTORY.with(|h| match h.borrow_mut().as_mut() {
+        Some(history) => Ok(f(history)),
+        None => Err("rating history is not initialized".to_string()),
+    })
+}
+
+pub fn record(asset: &str, record: RatingRecord) -> Result<(), String> {
+    let key = (asset_id(asset)?, record.timestamp);
+    // the map traps on values over its bound, so oversized records are refused up front
+    let size = record.to_bytes().len();
+    if size > MAX_RECORD_SIZE as usize {
+        return Err(format!(
+            "rating record of {} bytes exceeds {} bytes",
+            size, MAX_RECORD_SIZE
+        ));
+    }
+    with_history(|history| {
+        history.insert(key, record);
+    })
+}
+
+pub fn query_between(asset: &str, options: QueryOptions) -> Result<Vec<RatingRecord>, String> {
+    let id = asset_id(asset)?;
+    let from = options.from_timestamp.unwrap_or(0).max(0) as u64;
+    let to = options
+        .to_timestamp
+        .map(|t| t.max(0) as u64)
+        .unwrap_or(u64::MAX);
+    if from > to {
+        return Ok(vec![]);
+    }
+
+    with_history(|history| {
+        history
+            .range((id, from)..=(id, to))
+            .map(|(_, record)| record)
+            .collect()
+    })
+}
+
+pub fn last(asset: &str) -> Result<Option<RatingRecord>, String> {
+    let id = asset_id(asset)?;
+    // seeks straight to the newest record instead of walking the asset's history
+    with_history(|history| {
+        history
+            .iter_upper_bound(&(id, u64::MAX))
+            .next()
+            .filter(|((key, _), _)| *key == id)
+            .map(|(_, record)| record)
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::scale::{Breakpoint, MAX_BREAKPOINTS};
+    use ic_stable_structures::memory_manager::{MemoryId, MemoryManager};
+
+    pub(crate) fn init() {
+        let manager = MemoryManager::init(DefaultMemoryImpl::default());
+        init_history(manager.get(MemoryId::new(0)));
+    }
+
+    pub(crate) fn uninit() {
+        HISTORY.with(|h| *h.borrow_mut() = None);
+    }
+
+    fn rating_record(timestamp: u64, rating: f64) -> RatingRecord {
+        RatingRecord {
+            timestamp,
+            rating,
+            ..Default::default()
+        }
+    }
+
+    fn timestamps(records: &[RatingRecord]) -> Vec<u64> {
+        records.iter().map(|r| r.timestamp).collect()
+    }
+
+    #[test]
+    fn test_uninitialized() {
+        assert!(record("usdc", rating_record(1, 4.0)).is_err());
+    }
+
+    #[test]
+    fn test_query_between() {
+        init();
+        for t in [3600, 7200, 10800, 14400] {
+            record("usdc", rating_record(t, 4.0)).unwrap();
+        }
+        record("usdt", rating_record(7200, 4.8)).unwrap();
+
+        let options = QueryOptions {
+            from_timestamp: Some(7200),
+            to_timestamp: Some(10800),
+        };
+        let result = query_between("usdc", options).unwrap();
+        assert_eq!(timestamps(&result), vec![7200, 10800]);
+
+        let result = query_between("usdc", QueryOptions::default()).unwrap();
+        assert_eq!(timestamps(&result), vec![3600, 7200, 10800, 14400]);
+
+        let result = query_between("usdt", QueryOptions::default()).unwrap();
+        assert_eq!(result, vec![rating_record(7200, 4.8)]);
+    }
+
+    #[test]
+    fn test_empty_range() {
+        init();
+        record("usdc", rating_record(3600, 4.0)).unwrap();
+        let options = QueryOptions {
+            from_timestamp: Some(7200),
+            to_timestamp: Some(3600),
+        };
+        assert_eq!(query_between("usdc", options).unwrap(), vec![]);
+        assert_eq!(
+            query_between("dai", QueryOptions::default()).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_last() {
+        init();
+        record("usdc", rating_record(7200, 4.1)).unwrap();
+        record("usdc", rating_record(3600, 4.0)).unwrap();
+        assert_eq!(last("usdc").unwrap(), Some(rating_record(7200, 4.1)));
+        assert_eq!(last("dai").unwrap(), None);
+        // the nearest key below belongs to another asset
+        assert_eq!(last("usdt").unwrap(), None);
+    }
+
+    #[test]
+    fn test_oversized_record() {
+        init();
+        let record_of = |n: usize| RatingRecord {
+            scores: vec![0.5; n],
+            ..rating_record(1, 4.0)
+        };
+        assert!(record("usdc", record_of(7)).is_ok());
+        assert!(record("usdc", record_of(200)).is_err());
+        // the largest configuration a record can carry still fits
+        let breakpoint = |i: usize| Breakpoint {
+            score: i as f64 / MAX_BREAKPOINTS as f64,
+            rating: 0.0,
+        };
+        let configured = RatingRecord {
+            grade: "AAA".to_string(),
+            aggregator: Some(Aggregator::WeakestLink),
+            weights: Some(Weights::equal(7)),
+            scale_conversion: Some(ScaleConversion::Piecewise(
+                (0..MAX_BREAKPOINTS).map(breakpoint).collect(),
+            )),
+            ..record_of(7)
+        };
+        assert!(record("usdc", configured).is_ok());
+    }
+
+    #[test]
+    fn test_asset_id_too_long() {
+        init();
+        let asset = "x".repeat(65);
+        assert!(record(&asset, rating_record(1, 4.0)).is_err());
+    }
+}
@@ -1,6 +1,36 @@
+mod aggregate;
+mod breakdown;
+mod confidence;
+mod grade;
+mod history;
+mod outlook;
 mod rating;
+mod scale;
+mod sensitivity;
+mod smoothing;
+mod state;
 mod types;
+mod weights;
+pub use aggregate::Aggregator;
+pub use breakdown::set_reason_threshold;
+pub use common::bootstrap::Interval;
+pub use confidence::set_max_interval_width;
+pub use grade::{set_grade_scale, Cutoff, Grade, GradeScale};
+pub use history::{init_history, last, query_between, QueryOptions, RatingRecord};
+pub use outlook::{set_outlook_config, Outlook, OutlookConfig, OutlookReport};
+pub use rating::{calculate, rating_of, CalculateArgs, LensValue};
+pub use scale::{set_scale_conversion, Breakpoint, ScaleConversion};
+pub use sensitivity::{sensitivity, SensitivityReport};
+pub use smoothing::{set_smoothing, Smoothing};
+pub use state::{config, init, init_config, Config};
+pub use weights::{set_weights, Weights};
 pub type CallCanisterArgs = types::RequestArgsType;
+// the datasource request is configured through the canister, since the targets are only
+// known once it is deployed
 pub fn call_args() -> CallCanisterArgs {
-    todo!()
+    state::call_args().unwrap_or_else(|e| ic_cdk::trap(&e))
+}
+
+pub fn set_call_args(args: CallCanisterArgs) -> Result<(), String> {
+    state::set_call_args(&args)
 }
@@ -0,0 +1,257 @@
+use candid::CandidType;
+use common::trend::least_squares;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    breakdown::COMPONENTS,
+    history::RatingRecord,
+    scale::ScaleConversion,
+    state::{config, update_config},
+};
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub enum Outlook {
+    Positive,
+    #[default]
+    Stable,
+    Negative,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct OutlookConfig {
+    // seconds of history the trend is fitted over
+    pub lookback: u64,
+    pub min_points: u32,
+    // rating points per day a trend must exceed to count
+    pub slope_threshold: f64,
+    // t-statistic of the slope a trend must exceed to count
+    pub significance: f64,
+}
+
+impl Default for OutlookConfig {
+    fn default() -> Self {
+        OutlookConfig {
+            lookback: 30 * 86400,
+            min_points: 7,
+            slope_threshold: 0.01,
+            significance: 2.0,
+        }
+    }
+}
+
+impl OutlookConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.lookback == 0 {
+            return Err("lookback must be positive".to_string());
+        }
+        // a slope's t-statistic needs at least one residual degree of freedom
+        if self.min_points < 3 {
+            return Err(format!(
+                "min_points must be at least 3, got {}",
+                self.min_points
+            ));
+        }
+        if !self.slope_threshold.is_finite() || self.slope_threshold < 0.0 {
+            return Err("slope_threshold must be finite and non-negative".to_string());
+        }
+        if !self.significance.is_finite() || self.significance < 0.0 {
+            return Err("significance must be finite and non-negative".to_string());
+        }
+        Ok(())
+    }
+
+    fn classify(&self, trend: &Trend) -> Outlook {
+        if trend.t_stat.abs() < self.significance || trend.slope.abs() < self.slope_threshold {
+            Outlook::Stable
+        } else if trend.slope > 0.0 {
+            Outlook::Positive
+        } else {
+            Outlook::Negative
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Trend {
+    // change per day
+    pub slope: f64,
+    pub t_stat: f64,
+    pub points: u32,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct ComponentOutlook {
+    pub name: String,
+    pub trend: Trend,
+    pub outlook: Outlook,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct OutlookReport {
+    pub outlook: Outlook,
+    pub trend: Trend,
+    pub components: Vec<ComponentOutlook>,
+}
+
+// least-squares slope of the rating over days; two points fit any line exactly, so a
+// trend needs a third
+pub fn trend(points: &[(f64, f64)]) -> Trend {
+    if points.len() < 3 {
+        return Trend {
+            points: points.len() as u32,
+            ..Default::default()
+        };
+    }
+
+    let (slope, t_stat) = least_squares(points);
+    Trend {
+        slope,
+        t_stat,
+        points: points.len() as u32,
+    }
+}
+
+// the rating trend decides the outlook; component trends show where it comes from
+pub fn outlook(
+    records: &[RatingRecord],
+    conversion: &ScaleConversion,
+    config: &OutlookConfig,
+) -> OutlookReport {
+    if records.len() < config.min_points as usize {
+        return OutlookReport::default();
+    }
+    let days = |record: &RatingRecord| record.timestamp as f64 / SECONDS_PER_DAY;
+
+    let points = records
+        .iter()
+        .map(|r| (days(r), r.rating))
+        .collect::<Vec<_>>();
+    let rating_trend = trend(&points);
+
+    let count = records.iter().map(|r| r.scores.len()).min().unwrap_or(0);
+    let components = (0..count)
+        .map(|i| {
+            let points = records
+                .iter()
+                .map(|r| (days(r), conversion.to_rating(r.scores[i])))
+                .collect::<Vec<_>>();
+            let trend = trend(&points);
+            ComponentOutlook {
+                name: COMPONENTS.get(i).cloned().unwrap_or("unknown").to_string(),
+                outlook: config.classify(&trend),
+                trend,
+            }
+        })
+        .collect();
+    OutlookReport {
+        outlook: config.classify(&rating_trend),
+        trend: rating_trend,
+        components,
+    }
+}
+
+pub fn set_outlook_config(outlook: OutlookConfig) -> Result<(), String> {
+    outlook.validate()?;
+    update_config(|c| c.outlook = Some(outlook))
+}
+
+pub fn outlook_config() -> OutlookConfig {
+    config().outlook.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+
+    const day: u64 = 86400;
+
+    // daily records with the given ratings; scores follow the rating on the 0-1 scale
+    fn daily(ratings: &[f64]) -> Vec<RatingRecord> {
+        ratings
+            .iter()
+            .enumerate()
+            .map(|(i, rating)| RatingRecord {
+                timestamp: i as u64 * day,
+                rating: *rating,
+                scores: vec![rating / 5.0, 1.0],
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trend_exact_line() {
+        let points = [(0.0, 1.0), (1.0, 3.0), (2.0, 5.0), (3.0, 7.0)];
+        let result = trend(&points);
+        assert_eq!(result.slope, 2.0);
+        assert_eq!(result.t_stat, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_trend_too_few_points() {
+        let result = trend(&[(0.0, 1.0), (1.0, 3.0)]);
+        assert_eq!(result.slope, 0.0);
+        assert_eq!(result.points, 2);
+    }
+
+    #[test]
+    fn test_negative_outlook() {
+        let records = daily(&[4.2, 4.15, 4.12, 4.05, 4.01, 3.96, 3.9, 3.87]);
+        let result = outlook(
+            &records,
+            &ScaleConversion::default(),
+            &OutlookConfig::default(),
+        );
+        assert_eq!(result.outlook, Outlook::Negative);
+        assert_eq!(result.components[0].name, "deviation");
+        assert_eq!(result.components[0].outlook, Outlook::Negative);
+        assert_eq!(result.components[1].outlook, Outlook::Stable);
+    }
+
+    #[test]
+    fn test_positive_outlook() {
+        let records = daily(&[3.6, 3.66, 3.7, 3.75, 3.82, 3.85, 3.9]);
+        let result = outlook(
+            &records,
+            &ScaleConversion::default(),
+            &OutlookConfig::default(),
+        );
+        assert_eq!(result.outlook, Outlook::Positive);
+    }
+
+    #[test]
+    fn test_noise_is_stable() {
+        let records = daily(&[4.0, 4.1, 3.95, 4.05, 3.9, 4.1, 4.0, 3.95]);
+        let result = outlook(
+            &records,
+            &ScaleConversion::default(),
+            &OutlookConfig::default(),
+        );
+        assert_eq!(result.outlook, Outlook::Stable);
+    }
+
+    #[test]
+    fn test_short_history_is_stable() {
+        let records = daily(&[4.2, 4.0, 3.8]);
+        let result = outlook(
+            &records,
+            &ScaleConversion::default(),
+            &OutlookConfig::default(),
+        );
+        assert_eq!(result, OutlookReport::default());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        state::tests::init();
+        let config = OutlookConfig {
+            min_points: 2,
+            ..Default::default()
+        };
+        assert!(set_outlook_config(config).is_err());
+        assert_eq!(outlook_config(), OutlookConfig::default());
+    }
+}
@@ -1,15 +1,173 @@
+use common::bootstrap::Interval;
+
+use crate::{
+    aggregate::Aggregator,
+    breakdown::{breakdown, ComponentBreakdown},
+    confidence::{low_confidence, rating_interval},
+    grade::{grade_scale, Grade},
+    history::{self, QueryOptions, RatingRecord},
+    outlook::{outlook, outlook_config, OutlookReport},
+    scale::scale_conversion,
+    smoothing::smoothing,
+    weights::{weights_for, Weights},
+};
+
 #[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
 pub struct LensValue {
-    pub dummy: u64,
+    pub rating: f64,
+    pub smoothed_rating: f64,
+    pub grade: Grade,
+    pub weights: Vec<f64>,
+    pub components: Vec<ComponentBreakdown>,
+    pub reasons: Vec<String>,
+    // trend-based outlook, only computed when the rating is tracked for an asset
+    pub outlook: Option<OutlookReport>,
+    // only when every component came with a bootstrap interval
+    pub interval: Option<Interval>,
+    pub low_confidence: bool,
 }
 #[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
 pub struct CalculateArgs {
-    pub dummy: u64,
+    // component lens outputs in [0, 1], in the order rating() takes them
+    pub lens_values: Vec<f64>,
+    // raw statistics reported by the same lenses, used for the breakdown only
+    pub statistics: Vec<f64>,
+    // bootstrap intervals of the lens outputs, in the same order
+    pub intervals: Vec<Interval>,
+    pub aggregator: Option<Aggregator>,
+    // when set, the result is appended to this asset's rating history
+    pub asset: Option<String>,
+}
+pub async fn calculate(_targets: Vec<String>, args: CalculateArgs) -> Result<LensValue, String> {
+    let aggregator = args.aggregator.unwrap_or_default();
+    let mut value = rate(
+        &args.lens_values,
+        &args.statistics,
+        &args.intervals,
+        &aggregator,
+    )?;
+    if let Some(asset) = args.asset {
+        let timestamp = ic_cdk::api::time() / 1_000_000_000;
+        track(&asset, &mut value, args.lens_values, &aggregator, timestamp)?;
+    }
+    Ok(value)
 }
-pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> LensValue {
-    todo!()
+
+// appends the rating to the asset's history and reports it as published
+fn track(
+    asset: &str,
+    value: &mut LensValue,
+    lens_values: Vec<f64>,
+    aggregator: &Aggregator,
+    timestamp: u64,
+) -> Result<(), String> {
+    let previous = history::last(asset)?;
+    let record = publish(previous.as_ref(), value, lens_values, aggregator, timestamp);
+    value.smoothed_rating = record.smoothed_rating;
+    value.grade.grade = record.grade.clone();
+    history::record(asset, record)?;
+
+    let config = outlook_config();
+    let options = QueryOptions {
+        from_timestamp: Some(timestamp.saturating_sub(config.lookback) as i64),
+        to_timestamp: None,
+    };
+    let records = history::query_between(asset, options)?;
+    value.outlook = Some(outlook(&records, &scale_conversion(), &config));
+    Ok(())
+}
+
+// smooths the new rating against the asset's last record and applies grade hysteresis
+fn publish(
+    previous: Option<&RatingRecord>,
+    value: &LensValue,
+    scores: Vec<f64>,
+    aggregator: &Aggregator,
+    timestamp: u64,
+) -> RatingRecord {
+    let smoothing = smoothing();
+    let smoothed_rating = smoothing.smooth(previous.map(|p| p.smoothed_rating), value.rating);
+    let (grade, pending) = smoothing.grade(&grade_scale(), previous, smoothed_rating);
+    RatingRecord {
+        timestamp,
+        rating: value.rating,
+        smoothed_rating,
+        grade: grade.grade,
+        pending,
+        scores,
+        aggregator: Some(aggregator.clone()),
+        weights: Some(Weights(value.weights.clone())),
+        scale_conversion: Some(scale_conversion()),
+    }
+}
+
+// rating and grade of component lens outputs under the configured weights and scale
+pub fn rating_of(lens_values: &[f64], aggregator: &Aggregator) -> Result<(f64, Grade), String> {
+    let value = rate(lens_values, &[], &[], aggregator)?;
+    Ok((value.rating, value.grade))
 }
 
+// the six rating() components, or seven with supply; statistics and intervals are
+// optional but must cover every component when given
+fn validate(lens_values: &[f64], statistics: &[f64], intervals: &[Interval]) -> Result<(), String> {
+    let n = lens_values.len();
+    if n != 6 && n != 7 {
+        return Err(format!(
+            "expected 6 component lens values, or 7 with supply, got {}",
+            n
+        ));
+    }
+    if !statistics.is_empty() && statistics.len() != n {
+        return Err(format!(
+            "expected {} statistics, got {}",
+            n,
+            statistics.len()
+        ));
+    }
+    if !intervals.is_empty() && intervals.len() != n {
+        return Err(format!("expected {} intervals, got {}", n, intervals.len()));
+    }
+    Ok(())
+}
+
+fn rate(
+    lens_values: &[f64],
+    statistics: &[f64],
+    intervals: &[Interval],
+    aggregator: &Aggregator,
+) -> Result<LensValue, String> {
+    validate(lens_values, statistics, intervals)?;
+    let conversion = scale_conversion();
+    let scores = lens_values
+        .iter()
+        .map(|value| conversion.to_rating(*value))
+        .collect::<Vec<f64>>();
+    let weights = weights_for(scores.len());
+    let rating = aggregator.aggregate(&scores, &weights);
+    let (components, reasons) = breakdown(
+        lens_values,
+        statistics,
+        &scores,
+        &weights,
+        aggregator,
+        rating,
+    );
+    let interval = (!intervals.is_empty())
+        .then(|| rating_interval(intervals, &conversion, aggregator, &weights));
+    Ok(LensValue {
+        rating,
+        smoothed_rating: rating,
+        grade: grade_scale().grade(rating),
+        weights: weights.0,
+        components,
+        reasons,
+        outlook: None,
+        low_confidence: interval.as_ref().is_some_and(low_confidence),
+        interval,
+    })
+}
+
+#[cfg(test)]
 fn rating(
     score_avedev: f64,
     score_var: f64,
@@ -18,21 +176,67 @@ fn rating(
     score_address: f64,
     score_txvol: f64,
 ) -> f64 {
-    let score_avedev = score_avedev.powf(1.0 / 6.0);
-    let score_var = score_var.powf(1.0 / 6.0);
-    let score_autcor = scoreautcor.powf(1.0 / 6.0);
-    let score_dexliq = score_dexliq.powf(1.0 / 6.0);
-    let score_address = score_address.powf(1.0 / 6.0);
-    let score_txvol = score_txvol.powf(1.0 / 6.0);
-
-    score_avedev * score_var * score_autcor * score_dexliq * score_address * score_txvol
+    let scores = [
+        score_avedev,
+        score_var,
+        scoreautcor,
+        score_dexliq,
+        score_address,
+        score_txvol,
+    ];
+    rating_with(&scores, &Aggregator::Geometric)
+}
+
+#[cfg(test)]
+fn rating_with_supply(
+    score_avedev: f64,
+    score_var: f64,
+    scoreautcor: f64,
+    score_dexliq: f64,
+    score_address: f64,
+    score_txvol: f64,
+    score_supply: f64,
+) -> f64 {
+    let scores = [
+        score_avedev,
+        score_var,
+        scoreautcor,
+        score_dexliq,
+        score_address,
+        score_txvol,
+        score_supply,
+    ];
+    rating_with(&scores, &Aggregator::Geometric)
+}
+
+#[cfg(test)]
+fn rating_with(scores: &[f64], aggregator: &Aggregator) -> f64 {
+    aggregator.aggregate(scores, &weights_for(scores.len()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        aggregate::weighted_geometric_mean,
+        history,
+        outlook::Outlook,
+        scale::{set_scale_conversion, Breakpoint, ScaleConversion},
+        smoothing::{set_smoothing, Smoothing},
+        state,
+        weights::set_weights,
+    };
 
     const usdc: [f64; 6] = [4.202794, 1.921468, 4.528004, 5.000000, 4.602019, 5.000000];
+    // lens outputs behind the usdc component scores
+    const usdc_lens: [f64; 6] = [
+        0.8405587657505329,
+        0.9056007028336818,
+        0.38429367814737314,
+        1.0,
+        0.9204038358579446,
+        1.0,
+    ];
     const usdt: [f64; 6] = [5.000000, 5.000000, 5.000000, 4.289800, 5.000000, 4.947025];
     const dai: [f64; 6] = [4.413040, 5.000000, 4.435097, 4.187879, 3.459199, 4.979646];
     const fdusd: [f64; 6] = [3.672643, 4.644562, 3.840032, 3.519591, 1.666917, 4.395644];
@@ -78,4 +282,218 @@ mod tests {
         let result = rating(fdusd[0], fdusd[1], fdusd[2], fdusd[3], fdusd[4], fdusd[5]);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
+
+    #[test]
+    fn test_equal_weights_match_default() {
+        let expected = rating(usdc[0], usdc[1], usdc[2], usdc[3], usdc[4], usdc[5]);
+        let result = weighted_geometric_mean(&usdc, &Weights::equal(6));
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_rating_weighted() {
+        state::tests::init();
+        // price stability weighs more than activity
+        let weights = Weights(vec![0.3, 0.25, 0.15, 0.1, 0.1, 0.1]);
+        set_weights(weights).unwrap();
+        let expected = 3.6512753229886346;
+        let result = rating(usdc[0], usdc[1], usdc[2], usdc[3], usdc[4], usdc[5]);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_aggregators() {
+        let geometric = rating_with(&usdc, &Aggregator::Geometric);
+        let arithmetic = rating_with(&usdc, &Aggregator::Arithmetic);
+        let weakest = rating_with(&usdc, &Aggregator::WeakestLink);
+        assert_eq!(geometric, 4.017856419662701);
+        assert!(arithmetic > geometric);
+        assert_eq!(weakest, usdc[1]);
+    }
+
+    #[test]
+    fn test_usdc_rate_lens_values() {
+        let expected = 4.01785648279077;
+        let result = rate(&usdc_lens, &[], &[], &Aggregator::Geometric).unwrap();
+        assert_eq!(
+            result.rating, expected,
+            "Expected {}, got {}",
+            expected, result.rating
+        );
+        assert_eq!(result.grade.grade, "A");
+        assert_eq!(result.reasons, vec!["HIGH_AUTOCORRELATION"]);
+        assert_eq!(result.components.len(), 6);
+        assert_eq!(result.weights, Weights::equal(6).0);
+        assert_eq!(result.interval, None);
+        assert!(!result.low_confidence);
+    }
+
+    #[test]
+    fn test_usdc_rate_intervals() {
+        let intervals = usdc_lens
+            .iter()
+            .map(|value| Interval {
+                lower: value * 0.8,
+                upper: *value,
+            })
+            .collect::<Vec<Interval>>();
+        let result = rate(&usdc_lens, &[], &intervals, &Aggregator::Geometric).unwrap();
+        let interval = result.interval.unwrap();
+        assert!(interval.lower < result.rating);
+        assert!((interval.upper - result.rating).abs() < 1e-12);
+        assert!(result.low_confidence);
+    }
+
+    #[test]
+    fn test_rate_rejects_component_count() {
+        assert!(rate(&[], &[], &[], &Aggregator::Geometric).is_err());
+        assert!(rate(&usdc_lens[..5], &[], &[], &Aggregator::Geometric).is_err());
+        assert!(rate(&[0.5; 8], &[], &[], &Aggregator::Geometric).is_err());
+        assert!(rate(&[0.5; 7], &[], &[], &Aggregator::Geometric).is_ok());
+    }
+
+    #[test]
+    fn test_rate_rejects_mismatched_inputs() {
+        let statistics = [0.001; 5];
+        assert!(rate(&usdc_lens, &statistics, &[], &Aggregator::Geometric).is_err());
+        let intervals = vec![Interval::default(); 7];
+        assert!(rate(&usdc_lens, &[], &intervals, &Aggregator::Geometric).is_err());
+    }
+
+    #[test]
+    fn test_track_appends_history() {
+        history::tests::init();
+        for (day, timestamp) in [86400, 172800].iter().enumerate() {
+            let mut value = rate(&usdc_lens, &[], &[], &Aggregator::Geometric).unwrap();
+            track(
+                "usdc",
+                &mut value,
+                usdc_lens.to_vec(),
+                &Aggregator::Geometric,
+                *timestamp,
+            )
+            .unwrap();
+            let records = history::query_between("usdc", QueryOptions::default()).unwrap();
+            assert_eq!(records.len(), day + 1);
+        }
+        let record = history::last("usdc").unwrap().unwrap();
+        assert_eq!(record.timestamp, 172800);
+        assert_eq!(record.scores, usdc_lens.to_vec());
+        assert_eq!(record.grade, "A");
+        assert_eq!(record.aggregator, Some(Aggregator::Geometric));
+    }
+
+    #[test]
+    fn test_track_smooths_against_history() {
+        state::tests::init();
+        history::tests::init();
+        set_smoothing(Smoothing {
+            alpha: Some(0.5),
+            ..Default::default()
+        })
+        .unwrap();
+        let mut first = rate(&[1.0; 6], &[], &[], &Aggregator::Geometric).unwrap();
+        track(
+            "usdc",
+            &mut first,
+            vec![1.0; 6],
+            &Aggregator::Geometric,
+            86400,
+        )
+        .unwrap();
+        assert_eq!(first.smoothed_rating, 5.0);
+
+        let mut second = rate(&[0.6; 6], &[], &[], &Aggregator::Geometric).unwrap();
+        track(
+            "usdc",
+            &mut second,
+            vec![0.6; 6],
+            &Aggregator::Geometric,
+            172800,
+        )
+        .unwrap();
+        assert_eq!(second.rating, 3.0);
+        assert_eq!(second.smoothed_rating, 4.0);
+        assert_eq!(second.grade.grade, "A");
+        let record = history::last("usdc").unwrap().unwrap();
+        assert_eq!(record.smoothed_rating, 4.0);
+    }
+
+    #[test]
+    fn test_track_reports_outlook() {
+        state::tests::init();
+        history::tests::init();
+        let untracked = rate(&usdc_lens, &[], &[], &Aggregator::Geometric).unwrap();
+        assert_eq!(untracked.outlook, None);
+
+        let mut value = LensValue::default();
+        for day in 0..8 {
+            let lens_values = [0.84 - 0.01 * day as f64; 6];
+            value = rate(&lens_values, &[], &[], &Aggregator::Geometric).unwrap();
+            track(
+                "usdc",
+                &mut value,
+                lens_values.to_vec(),
+                &Aggregator::Geometric,
+                (day + 1) * 86400,
+            )
+            .unwrap();
+        }
+        let report = value.outlook.unwrap();
+        assert_eq!(report.outlook, Outlook::Negative);
+        assert_eq!(report.trend.points, 8);
+    }
+
+    #[test]
+    fn test_track_without_history() {
+        history::tests::uninit();
+        let mut value = rate(&usdc_lens, &[], &[], &Aggregator::Geometric).unwrap();
+        assert!(track(
+            "usdc",
+            &mut value,
+            usdc_lens.to_vec(),
+            &Aggregator::Geometric,
+            86400
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_rate_piecewise() {
+        state::tests::init();
+        let conversion = ScaleConversion::Piecewise(vec![
+            Breakpoint {
+                score: 0.0,
+                rating: 0.0,
+            },
+            Breakpoint {
+                score: 0.5,
+                rating: 3.0,
+            },
+            Breakpoint {
+                score: 1.0,
+                rating: 5.0,
+            },
+        ]);
+        set_scale_conversion(conversion).unwrap();
+        let expected = rating(4.0, 4.0, 4.0, 4.0, 4.0, 4.0);
+        let result = rate(&[0.75; 6], &[], &[], &Aggregator::Geometric)
+            .unwrap()
+            .rating;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_rating_with_supply_same_as_components() {
+        let expected = 1.0;
+        let result = rating_with_supply(1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_rating_with_supply() {
+        let expected = 3.6367528001453495;
+        let result = rating_with_supply(usdc[0], usdc[1], usdc[2], usdc[3], usdc[4], usdc[5], 2.0);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
 }
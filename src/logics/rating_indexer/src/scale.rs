@@ -0,0 +1,156 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::state::{config, update_config};
+
+// component scores and ratings are expressed on a 0-5 scale, lens outputs on 0-1
+pub const RATING_MAX: f64 = 5.0;
+// every rating record stores the conversion, so it has to fit the record bound
+pub const MAX_BREAKPOINTS: usize = 16;
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Breakpoint {
+    pub score: f64,
+    pub rating: f64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, Default, PartialEq)]
+pub enum ScaleConversion {
+    #[default]
+    Linear,
+    // interpolates between breakpoints ordered by lens score
+    Piecewise(Vec<Breakpoint>),
+}
+
+impl ScaleConversion {
+    pub fn validate(&self) -> Result<(), String> {
+        let breakpoints = match self {
+            ScaleConversion::Linear => return Ok(()),
+            ScaleConversion::Piecewise(breakpoints) => breakpoints,
+        };
+        if breakpoints.len() < 2 || breakpoints.len() > MAX_BREAKPOINTS {
+            return Err(format!(
+                "piecewise conversion needs 2 to {} breakpoints, got {}",
+                MAX_BREAKPOINTS,
+                breakpoints.len()
+            ));
+        }
+        if breakpoints.windows(2).any(|w| w[0].score >= w[1].score) {
+            return Err("breakpoint scores must be strictly increasing".to_string());
+        }
+        if breakpoints.windows(2).any(|w| w[0].rating > w[1].rating) {
+            return Err("breakpoint ratings must not decrease".to_string());
+        }
+        if breakpoints
+            .iter()
+            .any(|b| !(0.0..=1.0).contains(&b.score) || !(0.0..=RATING_MAX).contains(&b.rating))
+        {
+            return Err(format!(
+                "breakpoints must map [0, 1] scores onto [0, {}] ratings",
+                RATING_MAX
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_rating(&self, score: f64) -> f64 {
+        let score = score.clamp(0.0, 1.0);
+        match self {
+            ScaleConversion::Linear => score * RATING_MAX,
+            ScaleConversion::Piecewise(breakpoints) => interpolate(breakpoints, score),
+        }
+    }
+}
+
+fn interpolate(breakpoints: &[Breakpoint], score: f64) -> f64 {
+    let first = &breakpoints[0];
+    let last = &breakpoints[breakpoints.len() - 1];
+    if score <= first.score {
+        return first.rating;
+    }
+    if score >= last.score {
+        return last.rating;
+    }
+
+    let segment = breakpoints
+        .windows(2)
+        .find(|w| score <= w[1].score)
+        .unwrap();
+    let (lower, upper) = (&segment[0], &segment[1]);
+    let position = (score - lower.score) / (upper.score - lower.score);
+    lower.rating + position * (upper.rating - lower.rating)
+}
+
+pub fn set_scale_conversion(conversion: ScaleConversion) -> Result<(), String> {
+    conversion.validate()?;
+    update_config(|c| c.scale_conversion = Some(conversion))
+}
+
+pub fn scale_conversion() -> ScaleConversion {
+    config().scale_conversion.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+
+    fn breakpoints() -> Vec<Breakpoint> {
+        vec![
+            Breakpoint {
+                score: 0.0,
+                rating: 0.0,
+            },
+            Breakpoint {
+                score: 0.5,
+                rating: 3.0,
+            },
+            Breakpoint {
+                score: 1.0,
+                rating: 5.0,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_linear() {
+        let expected = 4.5;
+        let result = ScaleConversion::Linear.to_rating(0.9);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_linear_clamps() {
+        assert_eq!(ScaleConversion::Linear.to_rating(1.5), RATING_MAX);
+        assert_eq!(ScaleConversion::Linear.to_rating(-0.5), 0.0);
+    }
+
+    #[test]
+    fn test_piecewise() {
+        let conversion = ScaleConversion::Piecewise(breakpoints());
+        assert_eq!(conversion.to_rating(0.25), 1.5);
+        assert_eq!(conversion.to_rating(0.5), 3.0);
+        assert_eq!(conversion.to_rating(0.75), 4.0);
+        assert_eq!(conversion.to_rating(1.0), 5.0);
+    }
+
+    #[test]
+    fn test_piecewise_validation() {
+        assert!(ScaleConversion::Piecewise(breakpoints()).validate().is_ok());
+        let mut unordered = breakpoints();
+        unordered.swap(0, 1);
+        assert!(ScaleConversion::Piecewise(unordered).validate().is_err());
+        let mut out_of_range = breakpoints();
+        out_of_range[2].rating = 6.0;
+        assert!(ScaleConversion::Piecewise(out_of_range).validate().is_err());
+        assert!(ScaleConversion::Piecewise(vec![]).validate().is_err());
+    }
+
+    #[test]
+    fn test_stored_conversion() {
+        state::tests::init();
+        let conversion = ScaleConversion::Piecewise(breakpoints());
+        set_scale_conversion(conversion.clone()).unwrap();
+        assert_eq!(scale_conversion(), conversion);
+    }
+}
@@ -0,0 +1,290 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aggregate::{weighted_geometric_mean, Aggregator},
+    breakdown::COMPONENTS,
+    grade::{grade_scale, Grade, GradeScale},
+    history,
+    scale::RATING_MAX,
+    weights::Weights,
+};
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct ComponentSensitivity {
+    pub name: String,
+    pub rating: f64,
+    pub weight: f64,
+    // rating points gained per component rating point
+    pub partial: f64,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct RequiredChange {
+    pub name: String,
+    // component rating that puts the rating exactly on the boundary, holding the others
+    // fixed; None when the component alone cannot get there within [0, RATING_MAX]
+    pub required: Option<f64>,
+    pub change: Option<f64>,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct GradeBoundary {
+    pub rating: f64,
+    pub above: String,
+    pub below: String,
+    pub changes: Vec<RequiredChange>,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct SensitivityReport {
+    pub rating: f64,
+    pub grade: Grade,
+    pub components: Vec<ComponentSensitivity>,
+    pub boundaries: Vec<GradeBoundary>,
+}
+
+// with R = prod(s_i ^ w_i): dR/ds_i = w_i * R / s_i, and R reaches a target T when
+// s_i alone moves to s_i * (T / R) ^ (1 / w_i)
+fn required(score: f64, weight: f64, rating: f64, target: f64) -> Option<f64> {
+    if weight == 0.0 || score == 0.0 || rating == 0.0 {
+        return None;
+    }
+    let required = score * (target / rating).powf(1.0 / weight);
+    (required.is_finite() && (0.0..=RATING_MAX).contains(&required)).then_some(required)
+}
+
+pub fn sensitivities(scores: &[f64], weights: &Weights, scale: &GradeScale) -> SensitivityReport {
+    let rating = weighted_geometric_mean(scores, weights);
+    let name = |i: usize| COMPONENTS.get(i).cloned().unwrap_or("unknown").to_string();
+
+    let components = scores
+        .iter()
+        .zip(weights.0.iter())
+        .enumerate()
+        .map(|(i, (score, weight))| ComponentSensitivity {
+            name: name(i),
+            rating: *score,
+            weight: *weight,
+            partial: if *score > 0.0 {
+                weight * rating / score
+            } else {
+                0.0
+            },
+        })
+        .collect();
+
+    let bands = scale.bands();
+    let boundaries = bands
+        .windows(2)
+        .map(|pair| {
+            let target = pair[0].min;
+            let changes = scores
+                .iter()
+                .zip(weights.0.iter())
+                .enumerate()
+                .map(|(i, (score, weight))| {
+                    let required = required(*score, *weight, rating, target);
+                    RequiredChange {
+                        name: name(i),
+                        required,
+                        change: required.map(|required| required - score),
+                    }
+                })
+                .collect();
+            GradeBoundary {
+                rating: target,
+                above: pair[0].grade.clone(),
+                below: pair[1].grade.clone(),
+                changes,
+            }
+        })
+        .collect();
+
+    SensitivityReport {
+        rating,
+        grade: scale.grade(rating),
+        components,
+        boundaries,
+    }
+}
+
+// sensitivities of an asset's latest recorded rating
+pub fn sensitivity(asset: &str) -> Result<SensitivityReport, String> {
+    let record = history::last(asset)?.ok_or(format!("no rating recorded for {}", asset))?;
+    // the partials below are those of the weighted geometric mean
+    if record.aggregator != Some(Aggregator::Geometric) {
+        return Err(format!(
+            "sensitivities are only available for geometric ratings, {} was rated with {:?}",
+            asset, record.aggregator
+        ));
+    }
+    // today's configuration may differ from the one the rating was computed under
+    let (weights, conversion) = match (record.weights, record.scale_conversion) {
+        (Some(weights), Some(conversion)) => (weights, conversion),
+        _ => {
+            return Err(format!(
+                "the latest rating of {} was recorded without its weights and scale conversion",
+                asset
+            ))
+        }
+    };
+    let scores = record
+        .scores
+        .iter()
+        .map(|value| conversion.to_rating(*value))
+        .collect::<Vec<f64>>();
+    Ok(sensitivities(&scores, &weights, &grade_scale()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        scale::{set_scale_conversion, Breakpoint, ScaleConversion},
+        state,
+        weights::set_weights,
+    };
+
+    const usdc: [f64; 6] = [
+        4.202793828752665,
+        4.528003514168409,
+        1.9214683907368657,
+        5.0,
+        4.602019179289723,
+        5.0,
+    ];
+
+    fn report() -> SensitivityReport {
+        sensitivities(&usdc, &Weights::equal(6), &GradeScale::default())
+    }
+
+    #[test]
+    fn test_partials() {
+        let result = report();
+        let rating = result.rating;
+        for component in &result.components {
+            // the elasticity of a weighted geometric mean is the weight
+            let elasticity = component.partial * component.rating / rating;
+            assert!((elasticity - component.weight).abs() < 1e-12);
+        }
+        // the weak autocorrelation component moves the rating the most per point
+        let max = result
+            .components
+            .iter()
+            .map(|c| c.partial)
+            .fold(0.0, f64::max);
+        assert_eq!(result.components[2].partial, max);
+    }
+
+    #[test]
+    fn test_required_change_reaches_boundary() {
+        let result = report();
+        let upgrade = result
+            .boundaries
+            .iter()
+            .find(|boundary| boundary.above == "A+")
+            .unwrap();
+        assert_eq!(upgrade.below, "A");
+        let change = &upgrade.changes[2];
+        assert_eq!(change.name, "autocorrelation");
+        let mut scores = usdc;
+        scores[2] = change.required.unwrap();
+        let rating = weighted_geometric_mean(&scores, &Weights::equal(6));
+        assert!((rating - upgrade.rating).abs() < 1e-9);
+        assert!(change.change.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_saturated_component_cannot_upgrade() {
+        let result = report();
+        let upgrade = &result.boundaries[0];
+        assert_eq!(upgrade.above, "AAA");
+        // liquidity is already at the top of the scale
+        assert_eq!(upgrade.changes[3].required, None);
+        assert_eq!(upgrade.changes[3].change, None);
+    }
+
+    #[test]
+    fn test_downgrade_needs_negative_change() {
+        let result = report();
+        let downgrade = result
+            .boundaries
+            .iter()
+            .find(|boundary| boundary.below == "A-")
+            .unwrap();
+        assert!(downgrade.changes.iter().all(|c| c.change.unwrap() < 0.0));
+    }
+
+    fn record(aggregator: Option<Aggregator>) {
+        let record = history::RatingRecord {
+            timestamp: 86400,
+            scores: usdc.iter().map(|rating| rating / RATING_MAX).collect(),
+            aggregator,
+            weights: Some(Weights::equal(6)),
+            scale_conversion: Some(ScaleConversion::Linear),
+            ..Default::default()
+        };
+        history::record("usdc", record).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_asset() {
+        history::tests::init();
+        assert!(sensitivity("usdc").is_err());
+    }
+
+    #[test]
+    fn test_recorded_geometric_rating() {
+        state::tests::init();
+        history::tests::init();
+        record(Some(Aggregator::Geometric));
+        let result = sensitivity("usdc").unwrap();
+        assert!((result.rating - report().rating).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_uses_recorded_configuration() {
+        state::tests::init();
+        history::tests::init();
+        record(Some(Aggregator::Geometric));
+        // configuration changed after the rating was recorded
+        set_weights(Weights(vec![0.5, 0.1, 0.1, 0.1, 0.1, 0.1])).unwrap();
+        set_scale_conversion(ScaleConversion::Piecewise(vec![
+            Breakpoint {
+                score: 0.0,
+                rating: 0.0,
+            },
+            Breakpoint {
+                score: 1.0,
+                rating: 4.0,
+            },
+        ]))
+        .unwrap();
+        let result = sensitivity("usdc").unwrap();
+        assert!((result.rating - report().rating).abs() < 1e-12);
+        assert_eq!(result.components[0].weight, 1.0 / 6.0);
+    }
+
+    #[test]
+    fn test_rejects_records_without_configuration() {
+        history::tests::init();
+        let record = history::RatingRecord {
+            timestamp: 86400,
+            scores: usdc.iter().map(|rating| rating / RATING_MAX).collect(),
+            aggregator: Some(Aggregator::Geometric),
+            ..Default::default()
+        };
+        history::record("usdc", record).unwrap();
+        assert!(sensitivity("usdc").is_err());
+    }
+
+    #[test]
+    fn test_rejects_other_aggregators() {
+        history::tests::init();
+        for aggregator in [None, Some(Aggregator::WeakestLink)] {
+            record(aggregator);
+            assert!(sensitivity("usdc").is_err());
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    grade::{Grade, GradeScale},
+    history::RatingRecord,
+    state::{config, update_config},
+};
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Smoothing {
+    // EMA weight of the newest rating, no smoothing when unset
+    pub alpha: Option<f64>,
+    // distance past the cut-off needed to change grade immediately
+    pub margin: Option<f64>,
+    // consecutive runs on the other side of the cut-off needed to change grade
+    pub confirmations: Option<u32>,
+}
+
+impl Smoothing {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(alpha) = self.alpha {
+            if !(alpha > 0.0 && alpha <= 1.0) {
+                return Err(format!("alpha must be in (0, 1], got {}", alpha));
+            }
+        }
+        if let Some(margin) = self.margin {
+            if !margin.is_finite() || margin < 0.0 {
+                return Err(format!("margin must be non-negative, got {}", margin));
+            }
+        }
+        if self.confirmations == Some(0) {
+            return Err("confirmations must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn smooth(&self, previous: Option<f64>, rating: f64) -> f64 {
+        match (self.alpha, previous) {
+            (Some(alpha), Some(previous)) => alpha * rating + (1.0 - alpha) * previous,
+            _ => rating,
+        }
+    }
+
+    // the published grade and the number of runs it has been challenged for
+    pub fn grade(
+        &self,
+        scale: &GradeScale,
+        previous: Option<&RatingRecord>,
+        smoothed: f64,
+    ) -> (Grade, u32) {
+        let candidate = scale.grade(smoothed);
+        let previous = match previous {
+            Some(previous) if previous.grade != candidate.grade => previous,
+            _ => return (candidate, 0),
+        };
+        if self.margin.is_none() && self.confirmations.is_none() {
+            return (candidate, 0);
+        }
+
+        let pending = previous.pending + 1;
+        let crossed = self.margin.is_some_and(|margin| {
+            scale.grade(smoothed + margin).grade != previous.grade
+                && scale.grade(smoothed - margin).grade != previous.grade
+        });
+        let confirmed = self.confirmations.is_some_and(|n| pending >= n);
+        if crossed || confirmed {
+            (candidate, 0)
+        } else {
+            let grade = Grade {
+                grade: previous.grade.clone(),
+                version: scale.version,
+            };
+            (grade, pending)
+        }
+    }
+}
+
+pub fn set_smoothing(smoothing: Smoothing) -> Result<(), String> {
+    smoothing.validate()?;
+    update_config(|c| c.smoothing = Some(smoothing))
+}
+
+pub fn smoothing() -> Smoothing {
+    config().smoothing.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+
+    fn published(grade: &str, smoothed_rating: f64, pending: u32) -> RatingRecord {
+        RatingRecord {
+            smoothed_rating,
+            grade: grade.to_string(),
+            pending,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ema() {
+        let smoothing = Smoothing {
+            alpha: Some(0.25),
+            ..Default::default()
+        };
+        assert_eq!(smoothing.smooth(None, 4.0), 4.0);
+        assert_eq!(smoothing.smooth(Some(4.0), 3.0), 3.75);
+        assert_eq!(Smoothing::default().smooth(Some(4.0), 3.0), 3.0);
+    }
+
+    #[test]
+    fn test_no_hysteresis() {
+        let scale = GradeScale::default();
+        let previous = published("A", 3.8, 0);
+        let (grade, pending) = Smoothing::default().grade(&scale, Some(&previous), 3.7);
+        assert_eq!(grade.grade, "BBB+");
+        assert_eq!(pending, 0);
+    }
+
+    #[test]
+    fn test_margin() {
+        let scale = GradeScale::default();
+        let smoothing = Smoothing {
+            margin: Some(0.05),
+            ..Default::default()
+        };
+        let previous = published("A-", 3.8, 0);
+        // just under the A- cut-off at 3.75
+        let (grade, pending) = smoothing.grade(&scale, Some(&previous), 3.72);
+        assert_eq!(grade.grade, "A-");
+        assert_eq!(pending, 1);
+        let (grade, pending) = smoothing.grade(&scale, Some(&previous), 3.69);
+        assert_eq!(grade.grade, "BBB+");
+        assert_eq!(pending, 0);
+    }
+
+    #[test]
+    fn test_confirmations() {
+        let scale = GradeScale::default();
+        let smoothing = Smoothing {
+            confirmations: Some(3),
+            ..Default::default()
+        };
+        let mut previous = published("A-", 3.8, 0);
+        for expected in ["A-", "A-", "BBB+"] {
+            let (grade, pending) = smoothing.grade(&scale, Some(&previous), 3.74);
+            assert_eq!(grade.grade, expected);
+            previous = published(&grade.grade, 3.74, pending);
+        }
+        assert_eq!(previous.pending, 0);
+    }
+
+    #[test]
+    fn test_return_resets_pending() {
+        let scale = GradeScale::default();
+        let smoothing = Smoothing {
+            confirmations: Some(3),
+            ..Default::default()
+        };
+        let previous = published("A-", 3.74, 2);
+        let (grade, pending) = smoothing.grade(&scale, Some(&previous), 3.76);
+        assert_eq!(grade.grade, "A-");
+        assert_eq!(pending, 0);
+    }
+
+    #[test]
+    fn test_invalid_smoothing() {
+        state::tests::init();
+        let invalid = [
+            Smoothing {
+                alpha: Some(0.0),
+                ..Default::default()
+            },
+            Smoothing {
+                margin: Some(-0.1),
+                ..Default::default()
+            },
+            Smoothing {
+                confirmations: Some(0),
+                ..Default::default()
+            },
+        ];
+        for smoothing in invalid {
+            assert!(set_smoothing(smoothing).is_err());
+        }
+        assert_eq!(smoothing(), Smoothing::default());
+    }
+
+    #[test]
+    fn test_stored_smoothing() {
+        state::tests::init();
+        let stored = Smoothing {
+            alpha: Some(0.5),
+            ..Default::default()
+        };
+        set_smoothing(stored.clone()).unwrap();
+        assert_eq!(smoothing(), stored);
+    }
+}
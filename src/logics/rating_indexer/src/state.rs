@@ -0,0 +1,166 @@
+use std::{borrow::Cow, cell::RefCell, collections::BTreeMap};
+
+use candid::{CandidType, Decode, Encode};
+use ic_stable_structures::{
+    memory_manager::{MemoryId, MemoryManager},
+    DefaultMemoryImpl, StableCell, Storable,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    grade::GradeScale,
+    history::{init_history, Memory},
+    outlook::OutlookConfig,
+    scale::ScaleConversion,
+    smoothing::Smoothing,
+    weights::Weights,
+};
+
+// rating configuration set through the canister; every field falls back to its default
+// when unset, so fields added later decode from configs written by older versions
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Config {
+    pub weights: Option<Weights>,
+    pub grade_scale: Option<GradeScale>,
+    pub scale_conversion: Option<ScaleConversion>,
+    pub reason_thresholds: Option<BTreeMap<String, f64>>,
+    pub smoothing: Option<Smoothing>,
+    pub outlook: Option<OutlookConfig>,
+    pub max_interval_width: Option<f64>,
+    // candid encoding of the request sent to the datasource, kept opaque so the config
+    // does not depend on the generated bindings
+    pub call_args: Option<Vec<u8>>,
+}
+
+impl Storable for Config {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+thread_local! {
+    static CONFIG: RefCell<Option<StableCell<Config, Memory>>> = const { RefCell::new(None) };
+}
+
+// the canister owns the memory manager and hands over a dedicated virtual memory, so
+// the configuration survives upgrades
+pub fn init_config(memory: Memory) -> Result<(), String> {
+    let cell = StableCell::init(memory, Config::default())
+        .map_err(|e| format!("failed to initialize rating config: {:?}", e))?;
+    CONFIG.with(|c| *c.borrow_mut() = Some(cell));
+    Ok(())
+}
+
+// called from the canister's init and post_upgrade hooks, before any rating is tracked;
+// the configuration and the history take the two ids from `first_id`, the first one the
+// generated canister leaves free
+pub fn init(memory_manager: &MemoryManager<DefaultMemoryImpl>, first_id: u8) -> Result<(), String> {
+    // MemoryId::new reserves u8::MAX
+    let history_id = first_id
+        .checked_add(1)
+        .filter(|&id| id < u8::MAX)
+        .ok_or(format!("no memory ids left after {}", first_id))?;
+    init_config(memory_manager.get(MemoryId::new(first_id)))?;
+    init_history(memory_manager.get(MemoryId::new(history_id)));
+    Ok(())
+}
+
+// the default configuration until the canister initializes its state
+pub fn config() -> Config {
+    CONFIG.with(|c| {
+        c.borrow()
+            .as_ref()
+            .map(|cell| cell.get().clone())
+            .unwrap_or_default()
+    })
+}
+
+pub fn update_config(f: impl FnOnce(&mut Config)) -> Result<(), String> {
+    CONFIG.with(|c| {
+        let mut c = c.borrow_mut();
+        let cell = c
+            .as_mut()
+            .ok_or("rating config is not initialized".to_string())?;
+        let mut config = cell.get().clone();
+        f(&mut config);
+        cell.set(config)
+            .map_err(|e| format!("failed to store rating config: {:?}", e))?;
+        Ok(())
+    })
+}
+
+pub fn set_call_args<T: CandidType>(args: &T) -> Result<(), String> {
+    let bytes = Encode!(args).map_err(|e| format!("failed to encode call args: {}", e))?;
+    update_config(|c| c.call_args = Some(bytes))
+}
+
+pub fn call_args<T: CandidType + for<'de> Deserialize<'de>>() -> Result<T, String> {
+    let bytes = config()
+        .call_args
+        .ok_or("call args are not configured".to_string())?;
+    Decode!(&bytes, T).map_err(|e| format!("failed to decode call args: {}", e))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::history::{self, QueryOptions};
+
+    // fresh state, so tests that change the configuration do not leak it into others
+    pub(crate) fn init() {
+        let manager = MemoryManager::init(DefaultMemoryImpl::default());
+        init_config(manager.get(MemoryId::new(0))).unwrap();
+    }
+
+    #[test]
+    fn test_init_state() {
+        let manager = MemoryManager::init(DefaultMemoryImpl::default());
+        super::init(&manager, 3).unwrap();
+        update_config(|c| c.weights = Some(Weights::equal(6))).unwrap();
+        assert_eq!(
+            history::query_between("usdc", QueryOptions::default()).unwrap(),
+            vec![]
+        );
+        super::init(&manager, 3).unwrap();
+        assert_eq!(config().weights, Some(Weights::equal(6)));
+        assert!(super::init(&manager, u8::MAX - 1).is_err());
+    }
+
+    #[derive(Debug, CandidType, Deserialize, PartialEq)]
+    struct Request {
+        targets: Vec<String>,
+    }
+
+    #[test]
+    fn test_call_args() {
+        init();
+        assert!(call_args::<Request>().is_err());
+        let request = Request {
+            targets: vec!["aaaaa-aa".to_string()],
+        };
+        set_call_args(&request).unwrap();
+        assert_eq!(call_args::<Request>().unwrap(), request);
+        assert!(call_args::<Vec<u64>>().is_err());
+    }
+
+    #[test]
+    fn test_defaults_before_init() {
+        CONFIG.with(|c| *c.borrow_mut() = None);
+        assert_eq!(config(), Config::default());
+        assert!(update_config(|c| c.weights = Some(Weights::equal(6))).is_err());
+    }
+
+    #[test]
+    fn test_config_survives_reload() {
+        let manager = MemoryManager::init(DefaultMemoryImpl::default());
+        init_config(manager.get(MemoryId::new(0))).unwrap();
+        update_config(|c| c.weights = Some(Weights::equal(7))).unwrap();
+        // an upgrade re-initializes the cell from the same memory
+        init_config(manager.get(MemoryId::new(0))).unwrap();
+        assert_eq!(config().weights, Some(Weights::equal(7)));
+    }
+}
@@ -0,0 +1,87 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::state::{config, update_config};
+
+// tolerance for weights that are meant to sum to 1 but were entered as decimals
+const SUM_TOLERANCE: f64 = 1e-9;
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Weights(pub Vec<f64>);
+
+impl Weights {
+    pub fn equal(n: usize) -> Self {
+        Weights(vec![1.0 / n as f64; n])
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.0.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err("weights must be finite and non-negative".to_string());
+        }
+        let sum: f64 = self.0.iter().sum();
+        if (sum - 1.0).abs() > SUM_TOLERANCE {
+            return Err(format!("weights must sum to 1, got {}", sum));
+        }
+        Ok(())
+    }
+}
+
+// six weights configure rating(), seven configure rating_with_supply()
+pub fn set_weights(weights: Weights) -> Result<(), String> {
+    if weights.0.len() != 6 && weights.0.len() != 7 {
+        return Err(format!("expected 6 or 7 weights, got {}", weights.0.len()));
+    }
+    weights.validate()?;
+    update_config(|c| c.weights = Some(weights))
+}
+
+// stored weights when they match the number of components, equal weights otherwise
+pub fn weights_for(n: usize) -> Weights {
+    match config().weights {
+        Some(weights) if weights.0.len() == n => weights,
+        _ => Weights::equal(n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+
+    #[test]
+    fn test_equal_weights_are_valid() {
+        assert!(Weights::equal(6).validate().is_ok());
+        assert!(Weights::equal(7).validate().is_ok());
+    }
+
+    #[test]
+    fn test_sum_must_be_one() {
+        let weights = Weights(vec![0.3, 0.3, 0.1, 0.1, 0.1, 0.2]);
+        assert!(weights.validate().is_err());
+    }
+
+    #[test]
+    fn test_negative_weight() {
+        let weights = Weights(vec![0.5, 0.5, 0.2, -0.2, 0.0, 0.0]);
+        assert!(weights.validate().is_err());
+    }
+
+    #[test]
+    fn test_stored_weights() {
+        state::tests::init();
+        let weights = Weights(vec![0.3, 0.3, 0.1, 0.1, 0.1, 0.1]);
+        set_weights(weights.clone()).unwrap();
+        assert_eq!(weights_for(6), weights);
+        assert_eq!(weights_for(7), Weights::equal(7));
+        state::tests::init();
+        assert_eq!(weights_for(6), Weights::equal(6));
+    }
+
+    #[test]
+    fn test_wrong_length_is_rejected() {
+        state::tests::init();
+        let result = set_weights(Weights(vec![0.5, 0.5]));
+        assert!(result.is_err());
+        assert_eq!(weights_for(6), Weights::equal(6));
+    }
+}
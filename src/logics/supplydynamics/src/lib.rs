@@ -0,0 +1,324 @@
+use std::str::FromStr;
+
+use candid::Principal;
+use common::{bootstrap::Interval, calc, Args, CalculateInput};
+pub type CalculateArgs = Args;
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct LensValue {
+    pub value: f64,
+    // average supply change per period, and its minted and burned parts
+    pub net_mint_rate: f64,
+    pub mint_rate: f64,
+    pub burn_rate: f64,
+    pub contraction: f64,
+    pub drawdown: f64,
+    pub volatility: f64,
+    pub interval: Option<Interval>,
+}
+
+impl From<CalculateInput> for LensValue {
+    fn from(input: CalculateInput) -> Self {
+        // every statistic is taken on the flows, so resampling never joins two supply
+        // levels that were not adjacent
+        let value = net_flow_rates(&input.values);
+        let value_all_assets = input
+            .value_all_assets
+            .iter()
+            .map(|data| net_flow_rates(data))
+            .collect::<Vec<Vec<f64>>>();
+        let contraction = score_supply(&value, &value_all_assets, supply_contraction);
+        let drawdown = score_supply(&value, &value_all_assets, max_drawdown);
+        let volatility = score_supply(&value, &value_all_assets, supply_volatility);
+        let interval = input.bootstrap.as_ref().map(|bootstrap| {
+            bootstrap.interval(bootstrap.replicates(&value, &value_all_assets, input.index, score))
+        });
+        LensValue {
+            value: (contraction + drawdown + volatility) / 3.0,
+            net_mint_rate: net_mint_rate(&value),
+            mint_rate: mint_rate(&value),
+            burn_rate: burn_rate(&value),
+            contraction,
+            drawdown,
+            volatility,
+            interval,
+        }
+    }
+}
+
+pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> LensValue {
+    let target = Principal::from_str(&targets[0]).unwrap();
+    calc(target, args).await.unwrap()
+}
+
+// supply changes below one part per million are treated as noise
+const SUPPLY_RESOLUTION: f64 = 1e-6;
+
+// relative supply change between consecutive snapshots
+fn net_flow_rates(data: &[f64]) -> Vec<f64> {
+    data.windows(2)
+        .filter(|w| w[0] != 0.0)
+        .map(|w| (w[1] - w[0]) / w[0])
+        .collect()
+}
+
+fn net_mint_rate(rates: &[f64]) -> f64 {
+    let n = rates.len() as f64;
+    if n == 0.0 {
+        return 0.0;
+    }
+
+    rates.iter().sum::<f64>() / n
+}
+
+// minted share per period, averaged over every period including those that burned
+fn mint_rate(rates: &[f64]) -> f64 {
+    let minted = rates.iter().map(|&x| x.max(0.0)).collect::<Vec<f64>>();
+    net_mint_rate(&minted)
+}
+
+fn burn_rate(rates: &[f64]) -> f64 {
+    let burned = rates.iter().map(|&x| (-x).max(0.0)).collect::<Vec<f64>>();
+    net_mint_rate(&burned)
+}
+
+fn supply_contraction(rates: &[f64]) -> f64 {
+    (-net_mint_rate(rates)).max(0.0)
+}
+
+// drawdown of the supply path compounded from the flows, starting at 1.0
+fn max_drawdown(rates: &[f64]) -> f64 {
+    let mut level = 1.0;
+    let mut peak: f64 = 1.0;
+    let mut drawdown: f64 = 0.0;
+    for &rate in rates {
+        level *= 1.0 + rate;
+        peak = peak.max(level);
+        drawdown = drawdown.max((peak - level) / peak);
+    }
+    drawdown
+}
+
+fn supply_volatility(rates: &[f64]) -> f64 {
+    let n = rates.len() as f64;
+    if n == 0.0 {
+        return 0.0;
+    }
+
+    let mean = rates.iter().sum::<f64>() / n;
+    let variance = rates
+        .iter()
+        .map(|&x| {
+            let diff = x - mean;
+            diff * diff
+        })
+        .sum::<f64>()
+        / n;
+    variance.sqrt()
+}
+
+fn negative_log10_supply(stress: f64) -> f64 {
+    -stress.max(SUPPLY_RESOLUTION).log10()
+}
+
+fn max_negative_log10_supply(datasets: &[Vec<f64>], stat: fn(&[f64]) -> f64) -> f64 {
+    datasets
+        .iter()
+        .map(|data| negative_log10_supply(stat(data)))
+        .fold(0.0, f64::max)
+}
+
+fn score_supply(data: &[f64], datasets: &[Vec<f64>], stat: fn(&[f64]) -> f64) -> f64 {
+    let log10_supply = negative_log10_supply(stat(data));
+    let max_log10_supply = max_negative_log10_supply(datasets, stat);
+
+    if max_log10_supply == 0.0 {
+        0.0
+    } else {
+        log10_supply / max_log10_supply
+    }
+}
+
+fn score(data: &[f64], datasets: &[Vec<f64>]) -> f64 {
+    let contraction = score_supply(data, datasets, supply_contraction);
+    let drawdown = score_supply(data, datasets, max_drawdown);
+    let volatility = score_supply(data, datasets, supply_volatility);
+    (contraction + drawdown + volatility) / 3.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const usdc: [f64; 7] = [
+        25.91e9, 25.87e9, 25.12e9, 24.33e9, 24.41e9, 24.02e9, 24.18e9,
+    ];
+    const usdt: [f64; 7] = [
+        83.12e9, 83.30e9, 83.52e9, 83.49e9, 83.71e9, 83.95e9, 84.10e9,
+    ];
+    const dai: [f64; 7] = [5.34e9, 5.35e9, 5.31e9, 5.29e9, 5.33e9, 5.36e9, 5.34e9];
+    const fdusd: [f64; 7] = [1.02e9, 1.48e9, 2.11e9, 2.06e9, 2.54e9, 2.49e9, 2.61e9];
+
+    #[test]
+    fn test_empty_slice() {
+        let data = [];
+        let expected = 0.0;
+        let result = net_mint_rate(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_all_elements_same() {
+        let data = net_flow_rates(&[1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0]);
+        let expected = 0.0;
+        let result = supply_volatility(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_growing_supply_has_no_drawdown() {
+        let data = net_flow_rates(&[1.0, 2.0, 3.0, 4.0]);
+        let expected = 0.0;
+        let result = max_drawdown(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_net_mint_rate() {
+        let data = net_flow_rates(&usdc);
+        let expected = -0.01133529677412949;
+        let result = net_mint_rate(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_mint_and_burn_make_up_net_rate() {
+        let data = net_flow_rates(&dai);
+        let expected = net_mint_rate(&data);
+        let result = mint_rate(&data) - burn_rate(&data);
+        assert!(
+            (result - expected).abs() < 1e-15,
+            "Expected {}, got {}",
+            expected,
+            result
+        );
+        assert!(mint_rate(&data) > 0.0 && burn_rate(&data) > 0.0);
+    }
+
+    #[test]
+    fn test_lens_reports_rates() {
+        let input = CalculateInput {
+            values: usdc.to_vec(),
+            value_all_assets: vec![usdc.to_vec(), usdt.to_vec()],
+            ..Default::default()
+        };
+        let result = LensValue::from(input);
+        let expected = -0.01133529677412949;
+        assert_eq!(
+            result.net_mint_rate, expected,
+            "Expected {}, got {}",
+            expected, result.net_mint_rate
+        );
+        assert!(result.burn_rate > result.mint_rate);
+    }
+
+    #[test]
+    fn test_usdc_drawdown() {
+        let data = net_flow_rates(&usdc);
+        let expected = 0.07294480895407185;
+        let result = max_drawdown(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_dai_drawdown() {
+        let data = net_flow_rates(&dai);
+        let expected = 0.011214953271028066;
+        let result = max_drawdown(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_fdusd_volatility() {
+        let data = net_flow_rates(&fdusd);
+        let expected = 0.19796398248835628;
+        let result = supply_volatility(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_usdc() {
+        let input = CalculateInput {
+            values: usdc.to_vec(),
+            value_all_assets: vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()],
+            ..Default::default()
+        };
+        let expected = 0.4230799558215177;
+        let result = LensValue::from(input).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_usdt() {
+        let input = CalculateInput {
+            values: usdt.to_vec(),
+            value_all_assets: vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()],
+            ..Default::default()
+        };
+        let expected = 1.0;
+        let result = LensValue::from(input).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_dai() {
+        let input = CalculateInput {
+            values: dai.to_vec(),
+            value_all_assets: vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()],
+            ..Default::default()
+        };
+        let expected = 0.7771617252387886;
+        let result = LensValue::from(input).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_fdusd() {
+        let input = CalculateInput {
+            values: fdusd.to_vec(),
+            value_all_assets: vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()],
+            ..Default::default()
+        };
+        let expected = 0.5698080957821577;
+        let result = LensValue::from(input).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_interval_fdusd() {
+        let input = CalculateInput {
+            values: fdusd.to_vec(),
+            value_all_assets: vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()],
+            bootstrap: Some(common::bootstrap::Bootstrap::default()),
+            ..Default::default()
+        };
+        let result = LensValue::from(input);
+        let interval = result.interval.unwrap();
+        assert!(
+            interval.lower <= result.value && result.value <= interval.upper,
+            "Expected {} inside {:?}",
+            result.value,
+            interval
+        );
+        assert!(interval.width() > 0.0, "got {:?}", interval);
+    }
+
+    #[test]
+    fn test_no_interval_without_bootstrap() {
+        let input = CalculateInput {
+            values: usdc.to_vec(),
+            value_all_assets: vec![usdc.to_vec(), usdt.to_vec()],
+            ..Default::default()
+        };
+        assert_eq!(LensValue::from(input).interval, None);
+    }
+}
@@ -0,0 +1,440 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use candid::Principal;
+use common::{
+    bootstrap::{Bootstrap, Interval},
+    query_timestamped,
+};
+#[derive(
+    Clone, Debug, Default, PartialEq, candid :: CandidType, serde :: Deserialize, serde :: Serialize,
+)]
+pub struct AssetIds {
+    pub volume: String,
+    pub liquidity: String,
+    pub supply: String,
+}
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct CalculateArgs {
+    pub asset: AssetIds,
+    pub peers: Vec<AssetIds>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub interval_sec: Option<u64>,
+    // confidence intervals are only computed when requested
+    pub bootstrap: Option<Bootstrap>,
+}
+#[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
+pub struct LensValue {
+    pub value: f64,
+    pub volume_to_liquidity: f64,
+    pub volume_to_supply: f64,
+    pub wash_like: bool,
+    pub illiquid: bool,
+    pub interval: Option<Interval>,
+}
+
+pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> Result<LensValue, String> {
+    let target = Principal::from_str(&targets[0]).map_err(|e| e.to_string())?;
+    let interval = interval(&args)?;
+    if let Some(bootstrap) = &args.bootstrap {
+        bootstrap.validate()?;
+    }
+    let index = args.peers.iter().position(|ids| *ids == args.asset);
+
+    let asset = fetch_turnover(target, &args.asset, args.from, args.to, interval).await?;
+    let mut peers = vec![];
+    for ids in args.peers.iter() {
+        peers.push(fetch_turnover(target, ids, args.from, args.to, interval).await?);
+    }
+    Ok(score_turnover(
+        &asset,
+        &peers,
+        index,
+        args.bootstrap.as_ref(),
+    ))
+}
+
+const DEFAULT_INTERVAL_SEC: u64 = 86400;
+// daily transfer volume above 1000x DEX liquidity looks like wash trading
+const WASH_TURNOVER: f64 = 1000.0;
+// daily volume below 0.5% of supply means most of the supply never trades
+const ILLIQUID_TURNOVER: f64 = 0.005;
+
+fn interval(args: &CalculateArgs) -> Result<u64, String> {
+    match args.interval_sec.unwrap_or(DEFAULT_INTERVAL_SEC) {
+        0 => Err("interval_sec must be positive".to_string()),
+        interval => Ok(interval),
+    }
+}
+
+fn decoded(id: &str, value: Option<f64>) -> Result<f64, String> {
+    value.ok_or(format!("failed to decode snapshot of {}", id))
+}
+
+struct Turnover {
+    volume_to_liquidity: Vec<f64>,
+    volume_to_supply: Vec<f64>,
+}
+
+async fn fetch_turnover(
+    target: Principal,
+    ids: &AssetIds,
+    from: Option<i64>,
+    to: Option<i64>,
+    interval: u64,
+) -> Result<Turnover, String> {
+    let volume = query_timestamped(target, ids.volume.clone(), from, to, |f| {
+        decoded(&ids.volume, f.value())
+    })
+    .await?;
+    let liquidity = query_timestamped(target, ids.liquidity.clone(), from, to, |f| {
+        decoded(&ids.liquidity, f.value_from_string())
+    })
+    .await?;
+    let supply = query_timestamped(target, ids.supply.clone(), from, to, |f| {
+        decoded(&ids.supply, f.value())
+    })
+    .await?;
+    Ok(Turnover {
+        volume_to_liquidity: turnover_ratios(&volume, &liquidity, interval),
+        volume_to_supply: turnover_ratios(&volume, &supply, interval),
+    })
+}
+
+fn bucket(series: &[(u64, f64)], interval: u64) -> BTreeMap<u64, f64> {
+    let mut buckets = BTreeMap::new();
+    let mut sorted = series.to_vec();
+    sorted.sort_by_key(|(timestamp, _)| *timestamp);
+    for (timestamp, value) in sorted {
+        buckets.insert(timestamp / interval, value);
+    }
+    buckets
+}
+
+fn turnover_ratios(
+    numerator: &[(u64, f64)],
+    denominator: &[(u64, f64)],
+    interval: u64,
+) -> Vec<f64> {
+    let numerator = bucket(numerator, interval);
+    let denominator = bucket(denominator, interval);
+    numerator
+        .iter()
+        .filter_map(|(key, n)| match denominator.get(key) {
+            Some(d) if *d != 0.0 => Some(n / d),
+            _ => None,
+        })
+        .collect()
+}
+
+fn average_turnover(data: &[f64]) -> f64 {
+    let n = data.len() as f64;
+    if n == 0.0 {
+        return 0.0;
+    }
+
+    let sum: f64 = data.iter().sum();
+    sum / n
+}
+
+fn log10_turnover(data: &[f64]) -> f64 {
+    let average = average_turnover(data);
+    average.log10()
+}
+
+fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+fn score_turnover_ratio(data: &[f64], datasets: &[Vec<f64>]) -> f64 {
+    let log10_turnover_peers = datasets
+        .iter()
+        .map(|data| log10_turnover(data))
+        .collect::<Vec<f64>>();
+    let median_log10_turnover = median(&log10_turnover_peers);
+    let distance = (log10_turnover(data) - median_log10_turnover).abs();
+    let max_distance = log10_turnover_peers
+        .iter()
+        .map(|x| (x - median_log10_turnover).abs())
+        .fold(0.0, f64::max);
+
+    if max_distance == 0.0 {
+        if distance == 0.0 {
+            1.0
+        } else {
+            0.0
+        }
+    } else {
+        (1.0 - distance / max_distance).max(0.0)
+    }
+}
+
+// both ratios are resampled with the same seed, so when their buckets line up each
+// replicate draws the same days for volume to liquidity and volume to supply
+fn turnover_interval(
+    asset: &Turnover,
+    liquidity_peers: &[Vec<f64>],
+    supply_peers: &[Vec<f64>],
+    index: Option<usize>,
+    bootstrap: &Bootstrap,
+) -> Interval {
+    let liquidity = bootstrap.replicates(
+        &asset.volume_to_liquidity,
+        liquidity_peers,
+        index,
+        score_turnover_ratio,
+    );
+    let supply = bootstrap.replicates(
+        &asset.volume_to_supply,
+        supply_peers,
+        index,
+        score_turnover_ratio,
+    );
+    bootstrap.interval(
+        liquidity
+            .iter()
+            .zip(supply.iter())
+            .map(|(l, s)| (l + s) / 2.0)
+            .collect(),
+    )
+}
+
+fn score_turnover(
+    asset: &Turnover,
+    peers: &[Turnover],
+    index: Option<usize>,
+    bootstrap: Option<&Bootstrap>,
+) -> LensValue {
+    let liquidity_peers = peers
+        .iter()
+        .map(|p| p.volume_to_liquidity.clone())
+        .collect::<Vec<Vec<f64>>>();
+    let supply_peers = peers
+        .iter()
+        .map(|p| p.volume_to_supply.clone())
+        .collect::<Vec<Vec<f64>>>();
+    let volume_to_liquidity = average_turnover(&asset.volume_to_liquidity);
+    let volume_to_supply = average_turnover(&asset.volume_to_supply);
+    let liquidity_score = score_turnover_ratio(&asset.volume_to_liquidity, &liquidity_peers);
+    let supply_score = score_turnover_ratio(&asset.volume_to_supply, &supply_peers);
+    LensValue {
+        value: (liquidity_score + supply_score) / 2.0,
+        volume_to_liquidity,
+        volume_to_supply,
+        wash_like: volume_to_liquidity > WASH_TURNOVER,
+        illiquid: volume_to_supply < ILLIQUID_TURNOVER,
+        interval: bootstrap.map(|bootstrap| {
+            turnover_interval(asset, &liquidity_peers, &supply_peers, index, bootstrap)
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86400;
+
+    const usdc_volume: [f64; 6] = [
+        11297494841.0,
+        16662147803.0,
+        7903871311.0,
+        14753101769.0,
+        7527660368.0,
+        9817033672.0,
+    ];
+    const usdc_liquidity: [f64; 6] = [
+        92852142.73,
+        96521991.91,
+        104605045.15,
+        67397382.39,
+        72825403.63,
+        71642046.15,
+    ];
+    const usdc_supply: [f64; 6] = [25.91e9, 25.87e9, 25.12e9, 24.33e9, 24.41e9, 24.02e9];
+    const usdt_volume: [f64; 6] = [
+        10595078509.0,
+        12406512875.0,
+        5989646841.0,
+        9102603766.0,
+        5563919565.0,
+        9521087992.0,
+    ];
+    const usdt_liquidity: [f64; 6] = [
+        2466954.95,
+        20692204.12,
+        2279293.81,
+        9899416.37,
+        1213691.66,
+        476400.51,
+    ];
+    const usdt_supply: [f64; 6] = [83.12e9, 83.30e9, 83.52e9, 83.49e9, 83.71e9, 83.95e9];
+    const fdusd_volume: [f64; 6] = [
+        919638661.0,
+        469195441.0,
+        210240247.0,
+        2153513563.0,
+        386958583.0,
+        544515.0,
+    ];
+    const fdusd_liquidity: [f64; 6] = [
+        370916.93, 339327.94, 330683.76, 340056.62, 338320.95, 350043.94,
+    ];
+    const fdusd_supply: [f64; 6] = [1.02e9, 1.48e9, 2.11e9, 2.06e9, 2.54e9, 2.49e9];
+
+    // liquidity and supply are snapshotted an hour after volume
+    fn daily(values: &[f64], offset: u64) -> Vec<(u64, f64)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (i as u64 * DAY + offset, v))
+            .collect()
+    }
+
+    fn turnover(volume: &[f64], liquidity: &[f64], supply: &[f64]) -> Turnover {
+        Turnover {
+            volume_to_liquidity: turnover_ratios(&daily(volume, 0), &daily(liquidity, 3600), DAY),
+            volume_to_supply: turnover_ratios(&daily(volume, 0), &daily(supply, 3600), DAY),
+        }
+    }
+
+    fn peers() -> Vec<Turnover> {
+        vec![
+            turnover(&usdc_volume, &usdc_liquidity, &usdc_supply),
+            turnover(&usdt_volume, &usdt_liquidity, &usdt_supply),
+            turnover(&fdusd_volume, &fdusd_liquidity, &fdusd_supply),
+        ]
+    }
+
+    #[test]
+    fn test_empty_slice() {
+        let data = [];
+        let expected = 0.0;
+        let result = average_turnover(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_misaligned_timestamps_are_dropped() {
+        let numerator = [(0, 10.0), (DAY, 20.0), (2 * DAY, 30.0)];
+        let denominator = [(3600, 5.0), (2 * DAY + 3600, 10.0)];
+        let expected = vec![2.0, 3.0];
+        let result = turnover_ratios(&numerator, &denominator, DAY);
+        assert_eq!(
+            result, expected,
+            "Expected {:?}, got {:?}",
+            expected, result
+        );
+    }
+
+    #[test]
+    fn test_zero_denominator() {
+        let numerator = [(0, 10.0)];
+        let denominator = [(0, 0.0)];
+        let expected: Vec<f64> = vec![];
+        let result = turnover_ratios(&numerator, &denominator, DAY);
+        assert_eq!(
+            result, expected,
+            "Expected {:?}, got {:?}",
+            expected, result
+        );
+    }
+
+    #[test]
+    fn test_zero_interval() {
+        let args = CalculateArgs {
+            interval_sec: Some(0),
+            ..Default::default()
+        };
+        assert!(interval(&args).is_err());
+        let expected = DEFAULT_INTERVAL_SEC;
+        let result = interval(&CalculateArgs::default()).unwrap();
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_last_value_in_bucket_wins() {
+        let series = [(DAY + 60, 2.0), (DAY, 1.0)];
+        let expected = Some(&2.0);
+        let result = bucket(&series, DAY);
+        assert_eq!(result.get(&1), expected);
+    }
+
+    #[test]
+    fn test_usdc_volume_to_liquidity() {
+        let data = turnover(&usdc_volume, &usdc_liquidity, &usdc_supply).volume_to_liquidity;
+        let expected = 138.1914189375422;
+        let result = average_turnover(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_volume_to_supply() {
+        let data = turnover(&usdc_volume, &usdc_liquidity, &usdc_supply).volume_to_supply;
+        let expected = 0.4530344676803168;
+        let result = average_turnover(&data);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_usdc() {
+        let asset = turnover(&usdc_volume, &usdc_liquidity, &usdc_supply);
+        let result = score_turnover(&asset, &peers(), None, None);
+        let expected = 0.47189692582423376;
+        assert_eq!(
+            result.value, expected,
+            "Expected {}, got {}",
+            expected, result.value
+        );
+        assert!(!result.wash_like);
+        assert!(!result.illiquid);
+    }
+
+    #[test]
+    fn test_score_usdt() {
+        let asset = turnover(&usdt_volume, &usdt_liquidity, &usdt_supply);
+        let result = score_turnover(&asset, &peers(), None, None);
+        let expected = 0.3101397030933763;
+        assert_eq!(
+            result.value, expected,
+            "Expected {}, got {}",
+            expected, result.value
+        );
+        assert!(result.wash_like);
+        assert!(!result.illiquid);
+    }
+
+    #[test]
+    fn test_score_interval_usdc() {
+        let asset = turnover(&usdc_volume, &usdc_liquidity, &usdc_supply);
+        let bootstrap = Bootstrap::default();
+        let result = score_turnover(&asset, &peers(), Some(0), Some(&bootstrap));
+        let interval = result.interval.unwrap();
+        assert!(
+            interval.lower <= result.value && result.value <= interval.upper,
+            "Expected {} inside {:?}",
+            result.value,
+            interval
+        );
+        assert!(interval.width() > 0.0, "got {:?}", interval);
+    }
+
+    #[test]
+    fn test_score_fdusd() {
+        let asset = turnover(&fdusd_volume, &fdusd_liquidity, &fdusd_supply);
+        let expected = 1.0;
+        let result = score_turnover(&asset, &peers(), None, None).value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+}
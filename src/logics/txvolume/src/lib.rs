@@ -1,51 +1,72 @@
-use txvolume_accessors::*;
+use common::{
+    bootstrap::Interval,
+    guard::Outcome,
+    metric::{self, Direction, Metric},
+    trend::{score_trend, trend},
+    weighting::{weighted_mean, Weighting},
+    Args, CalculateInput,
+};
+pub type CalculateArgs = Args;
 #[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
 pub struct LensValue {
-    pub dummy: u64,
+    pub value: f64,
+    pub outcome: Outcome,
+    pub statistic: f64,
+    pub interval: Option<Interval>,
+    pub trend: f64,
+    // growth of the second half of the window over the first
+    pub growth: f64,
+    // whether the log-linear slope behind the trend score is significant at 95%
+    pub significant: bool,
 }
-pub async fn calculate(targets: Vec<String>) -> LensValue {
-    let _result =
-        get_get_last_snapshot_in_sample_snapshot_indexer_icp(targets.get(0usize).unwrap().clone())
-            .await;
-    todo!()
+impl From<CalculateInput> for LensValue {
+    fn from(input: CalculateInput) -> Self {
+        let score = metric::score::<TxVolume>(&input);
+        let fitted = trend(&input.values);
+        let trend = score_trend(&input.values, &input.value_all_assets);
+        LensValue {
+            value: score.value,
+            outcome: score.outcome,
+            statistic: metric::statistic::<TxVolume>(&input),
+            interval: input
+                .bootstrap
+                .as_ref()
+                .map(|bootstrap| metric::interval::<TxVolume>(&input, bootstrap)),
+            trend,
+            growth: fitted.growth,
+            significant: fitted.significant,
+        }
+    }
+}
+
+pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> LensValue {
+    metric::calculate::<TxVolume, _>(targets, args).await
 }
 
-fn average_volume(data: &[f64]) -> f64 {
+fn average_volume(data: &[f64], weighting: &Weighting) -> f64 {
     let n = data.len() as f64;
     if n == 0.0 {
         return 0.0;
     }
 
-    let sum: f64 = data.iter().sum();
-    sum / n
+    weighted_mean(data, weighting)
 }
 
-fn log10_volume(data: &[f64]) -> f64 {
-    let average = average_volume(data);
-    average.log10()
-}
+pub struct TxVolume;
 
-fn max_log10_volume(datasets: &[Vec<f64>]) -> f64 {
-    datasets
-        .iter()
-        .map(|data| log10_volume(data))
-        .fold(0.0, f64::max)
-}
-
-fn score_volume(data: &[f64], datasets: &[Vec<f64>]) -> f64 {
-    let log10_volume = log10_volume(data);
-    let max_log10_volume = max_log10_volume(datasets);
+impl Metric for TxVolume {
+    const NAME: &'static str = "txvolume";
+    const DIRECTION: Direction = Direction::HigherIsBetter;
 
-    if max_log10_volume == 0.0 {
-        0.0
-    } else {
-        log10_volume / max_log10_volume
+    fn statistic(data: &[f64], weighting: &Weighting) -> f64 {
+        average_volume(data, weighting)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::normalize::Normalization;
 
     const usdc: [f64; 6] = [
         11297494841.0,
@@ -84,7 +105,7 @@ mod tests {
     fn test_empty_slice() {
         let data = [];
         let expected = 0.0;
-        let result = average_volume(&data);
+        let result = average_volume(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -92,7 +113,7 @@ mod tests {
     fn test_all_elements_same() {
         let data = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
         let expected = 1.0;
-        let result = average_volume(&data);
+        let result = average_volume(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -100,7 +121,7 @@ mod tests {
     fn test_usdc_6days() {
         let data = usdc;
         let expected = 11326884960.666666;
-        let result = average_volume(&data);
+        let result = average_volume(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -108,7 +129,7 @@ mod tests {
     fn test_usdt_6days() {
         let data = usdt;
         let expected = 8863141591.333334;
-        let result = average_volume(&data);
+        let result = average_volume(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -116,7 +137,7 @@ mod tests {
     fn test_usdc_log10() {
         let data = usdc;
         let expected = 10.054110489704426;
-        let result = log10_volume(&data);
+        let result = TxVolume::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -124,7 +145,7 @@ mod tests {
     fn test_usdt_log10() {
         let data = usdt;
         let expected = 9.947587687343765;
-        let result = log10_volume(&data);
+        let result = TxVolume::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -137,7 +158,13 @@ mod tests {
             fdusd.to_vec(),
         ];
         let expected = 1.0;
-        let result = score_volume(&usdc, &datasets);
+        let result = TxVolume::score(
+            &usdc,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -150,7 +177,13 @@ mod tests {
             fdusd.to_vec(),
         ];
         let expected = 0.9894050495596063;
-        let result = score_volume(&usdt, &datasets);
+        let result = TxVolume::score(
+            &usdt,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -163,7 +196,13 @@ mod tests {
             fdusd.to_vec(),
         ];
         let expected = 0.9959291287089729;
-        let result = score_volume(&dai, &datasets);
+        let result = TxVolume::score(
+            &dai,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -176,7 +215,132 @@ mod tests {
             fdusd.to_vec(),
         ];
         let expected = 0.8791288544937829;
-        let result = score_volume(&fdusd, &datasets);
+        let result = TxVolume::score(
+            &fdusd,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_slope() {
+        let data = usdc;
+        let expected = -0.0703392889099754;
+        let result = trend(&data).slope;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_trend_usdc() {
+        let datasets = vec![
+            usdc.to_vec(),
+            usdt.to_vec(),
+            dai.to_vec(),
+            fdusd.to_vec(),
+        ];
+        let expected = 0.5;
+        let result = score_trend(&usdc, &datasets);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_trend_fdusd() {
+        let datasets = vec![
+            usdc.to_vec(),
+            usdt.to_vec(),
+            dai.to_vec(),
+            fdusd.to_vec(),
+        ];
+        let expected = 0.5;
+        let result = score_trend(&fdusd, &datasets);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
+
+    #[test]
+    fn test_usdc_half_life() {
+        let data = usdc;
+        let expected = 10535303976.037546;
+        let result = average_volume(&data, &Weighting::HalfLife(2.0));
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_degenerate_input() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let cases = [
+            (vec![], Outcome::NoData),
+            (vec![0.0, 0.0], Outcome::ZeroInput),
+            (vec![1.0, -1.0], Outcome::NegativeInput),
+            (vec![1.0, f64::NAN], Outcome::NonFinite),
+            (vec![1.0, f64::INFINITY], Outcome::NonFinite),
+        ];
+        for (data, expected) in cases {
+            let result = TxVolume::score(
+                &data,
+                &datasets,
+                &Weighting::Uniform,
+                &Normalization::RelativeToMax,
+            );
+            assert_eq!(result.outcome, expected);
+            assert_eq!(result.value, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_score_clamped() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let result = TxVolume::score(
+            &[0.5, 0.5],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::Clamped);
+        assert_eq!(result.value, 0.0);
+    }
+
+    #[test]
+    fn test_score_degenerate_peers() {
+        let datasets = vec![[0.5, 0.5].to_vec(), [0.2, 0.2].to_vec()];
+        let result = TxVolume::score(
+            &[0.5, 0.5],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::DegeneratePeers);
+        assert_eq!(result.value, 0.0);
+    }
+
+    #[test]
+    fn test_score_interval_fdusd() {
+        let input = CalculateInput {
+            values: fdusd.to_vec(),
+            value_all_assets: vec![
+                usdc.to_vec(),
+                usdt.to_vec(),
+                dai.to_vec(),
+                fdusd.to_vec(),
+            ],
+            bootstrap: Some(common::bootstrap::Bootstrap::default()),
+            ..Default::default()
+        };
+        let result = LensValue::from(input);
+        let interval = result.interval.unwrap();
+        assert!(interval.lower <= result.value && result.value <= interval.upper);
+        assert!(interval.width() > 0.0, "got {:?}", interval);
+    }
+
+    #[test]
+    fn test_no_interval_without_bootstrap() {
+        let input = CalculateInput {
+            values: usdc.to_vec(),
+            value_all_assets: vec![usdc.to_vec(), usdt.to_vec()],
+            ..Default::default()
+        };
+        assert_eq!(LensValue::from(input).interval, None);
+    }
 }
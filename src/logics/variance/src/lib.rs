@@ -1,86 +1,89 @@
-use std::str::FromStr;
-
-use candid::Principal;
-use common::{calc, Args, CalculateInput};
+use common::{
+    bootstrap::Interval,
+    guard::Outcome,
+    metric::{self, Direction, Metric},
+    weighting::{weighted_mean, Weighting},
+    Args, CalculateInput,
+};
 use variance_accessors::*;
 pub type CalculateArgs = Args;
 #[derive(Clone, Debug, Default, candid :: CandidType, serde :: Deserialize, serde :: Serialize)]
 pub struct LensValue {
     pub value: f64,
+    pub outcome: Outcome,
+    pub statistic: f64,
+    pub interval: Option<Interval>,
 }
 impl From<CalculateInput> for LensValue {
     fn from(input: CalculateInput) -> Self {
-        let value = input.values;
-        let value_all_assets = input.value_all_assets;
-        let score = score_variance(&value, &value_all_assets);
-        LensValue { value: score }
+        let score = metric::score::<Variance>(&input);
+        LensValue {
+            value: score.value,
+            outcome: score.outcome,
+            statistic: metric::statistic::<Variance>(&input),
+            interval: input
+                .bootstrap
+                .as_ref()
+                .map(|bootstrap| metric::interval::<Variance>(&input, bootstrap)),
+        }
     }
 }
 
 pub async fn calculate(targets: Vec<String>, args: CalculateArgs) -> LensValue {
-    let target = Principal::from_str(&targets[0]).unwrap();
-    calc(target, args).await.unwrap()
+    metric::calculate::<Variance, _>(targets, args).await
 }
 
-fn mean(data: &[f64]) -> f64 {
+fn mean(data: &[f64], weighting: &Weighting) -> f64 {
     if data.is_empty() {
         return 0.0;
     }
-    let sum: f64 = data.iter().sum();
-    let n = data.len() as f64;
 
-    sum / n
+    weighted_mean(data, weighting)
 }
 
-fn variance(data: &[f64]) -> f64 {
-    let data_mean = mean(data);
+fn variance(data: &[f64], weighting: &Weighting) -> f64 {
+    let data_mean = mean(data, weighting);
     let n = data.len() as f64;
 
     if n == 0.0 {
         return 0.0;
     }
 
-    let variance = data
+    let squared_diff = data
         .iter()
         .map(|&x| {
             let diff = x - data_mean;
             diff * diff
         })
-        .sum::<f64>()
-        / n;
-    variance
+        .collect::<Vec<f64>>();
+    weighted_mean(&squared_diff, weighting)
 }
 
-fn negative_log10_variance(data: &[f64]) -> f64 {
-    let variance = variance(data);
-    if variance == 0.0 {
-        0.0
-    } else {
-        -variance.log10()
-    }
-}
+// squared 6-decimal price tick; a flat series scores as one tick of dispersion
+const VARIANCE_RESOLUTION: f64 = 1e-12;
 
-fn max_negative_log10_variance(datasets: &[Vec<f64>]) -> f64 {
-    datasets
-        .iter()
-        .map(|data| negative_log10_variance(data))
-        .fold(0.0, f64::max)
-}
+pub struct Variance;
 
-fn score_variance(data: &[f64], datasets: &[Vec<f64>]) -> f64 {
-    let log10_variance = negative_log10_variance(data);
-    let max_log10_variance = max_negative_log10_variance(datasets);
+impl Metric for Variance {
+    const NAME: &'static str = "variance";
+    const DIRECTION: Direction = Direction::LowerIsBetter;
+    const MIN_SAMPLES: usize = 2;
+    const RESOLUTION: f64 = VARIANCE_RESOLUTION;
 
-    if max_log10_variance == 0.0 {
-        0.0
-    } else {
-        log10_variance / max_log10_variance
+    fn statistic(data: &[f64], weighting: &Weighting) -> f64 {
+        variance(data, weighting)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::benchmark::{init_benchmarks, register_benchmark, Benchmark};
+    use common::normalize::Normalization;
+    use ic_stable_structures::{
+        memory_manager::{MemoryId, MemoryManager},
+        DefaultMemoryImpl,
+    };
 
     const usdc: [f64; 7] = [
         0.999482, 1.001000, 0.999570, 1.001000, 1.001000, 0.998959, 1.000000,
@@ -99,7 +102,7 @@ mod tests {
     fn test_empty_slice() {
         let data = [];
         let expected = 0.0;
-        let result = variance(&data);
+        let result = variance(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -107,15 +110,42 @@ mod tests {
     fn test_all_elements_same() {
         let data = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
         let expected = 0.0;
-        let result = variance(&data);
+        let result = variance(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
+    #[test]
+    fn test_perfect_peg_log10() {
+        let data = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+        let expected = 12.0;
+        let result = Variance::log10(&data, &Weighting::Uniform);
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_perfect_peg() {
+        let peg = [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
+        let datasets = vec![peg.to_vec(), usdc.to_vec(), usdt.to_vec(), dai.to_vec()];
+        let expected = 1.0;
+        let result = Variance::score(
+            &peg,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::Valid);
+        assert_eq!(
+            result.value, expected,
+            "Expected {}, got {}",
+            expected, result.value
+        );
+    }
+
     #[test]
     fn test_usdc_1week() {
         let data = usdc;
         let expected = 0.0000006272696734693033;
-        let result = variance(&data);
+        let result = variance(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -123,7 +153,7 @@ mod tests {
     fn test_usdt_1week() {
         let data = usdt;
         let expected = 0.0000001415482448979294;
-        let result = variance(&data);
+        let result = variance(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -131,7 +161,7 @@ mod tests {
     fn test_usdc_log10() {
         let data = usdc;
         let expected = 6.202545708737672;
-        let result = negative_log10_variance(&data);
+        let result = Variance::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -139,7 +169,7 @@ mod tests {
     fn test_usdt_log10() {
         let data = usdt;
         let expected = 6.849095511222015;
-        let result = negative_log10_variance(&data);
+        let result = Variance::log10(&data, &Weighting::Uniform);
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -147,7 +177,13 @@ mod tests {
     fn test_score_usdc() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 0.9056007028336818;
-        let result = score_variance(&usdc, &datasets);
+        let result = Variance::score(
+            &usdc,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -155,7 +191,69 @@ mod tests {
     fn test_score_usdt() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 1.0;
-        let result = score_variance(&usdt, &datasets);
+        let result = Variance::score(
+            &usdt,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_usdt_percentile_rank() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let expected = 0.875;
+        let result = Variance::score(
+            &usdt,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::PercentileRank,
+        )
+        .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_anchored_score_ignores_peer_changes() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let peers = datasets
+            .iter()
+            .map(|data| Variance::log10(data, &Weighting::Uniform))
+            .collect::<Vec<f64>>();
+        let manager = MemoryManager::init(DefaultMemoryImpl::default());
+        init_benchmarks(manager.get(MemoryId::new(0)));
+        register_benchmark(Variance::NAME, Benchmark::from_peers(1, &peers)).unwrap();
+        let expected = Variance::score(
+            &usdc,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
+        let fewer_peers = vec![usdc.to_vec(), dai.to_vec()];
+        let result = Variance::score(
+            &usdc,
+            &fewer_peers,
+            &Weighting::Uniform,
+            &Normalization::Anchored(1),
+        )
+        .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_score_usdt_min_max() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let expected = 1.0;
+        let result = Variance::score(
+            &usdt,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::MinMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -163,7 +261,13 @@ mod tests {
     fn test_score_dai() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 0.8870193377333394;
-        let result = score_variance(&dai, &datasets);
+        let result = Variance::score(
+            &dai,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
 
@@ -171,7 +275,69 @@ mod tests {
     fn test_score_fdusd() {
         let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
         let expected = 0.7680064070586001;
-        let result = score_variance(&fdusd, &datasets);
+        let result = Variance::score(
+            &fdusd,
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        )
+        .value;
+        assert_eq!(result, expected, "Expected {}, got {}", expected, result);
+    }
+
+    #[test]
+    fn test_usdc_half_life() {
+        let data = usdc;
+        let expected = 0.000000601327243343013;
+        let result = variance(&data, &Weighting::HalfLife(2.0));
         assert_eq!(result, expected, "Expected {}, got {}", expected, result);
     }
+
+    #[test]
+    fn test_score_degenerate_input() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let cases = [
+            (vec![], Outcome::NoData),
+            (vec![0.0, 0.0], Outcome::ZeroInput),
+            (vec![1.0, -1.0], Outcome::NegativeInput),
+            (vec![1.0, f64::NAN], Outcome::NonFinite),
+            (vec![1.0, f64::INFINITY], Outcome::NonFinite),
+        ];
+        for (data, expected) in cases {
+            let result = Variance::score(
+                &data,
+                &datasets,
+                &Weighting::Uniform,
+                &Normalization::RelativeToMax,
+            );
+            assert_eq!(result.outcome, expected);
+            assert_eq!(result.value, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_score_clamped() {
+        let datasets = vec![usdc.to_vec(), usdt.to_vec(), dai.to_vec(), fdusd.to_vec()];
+        let result = Variance::score(
+            &[0.0, 10.0],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::Clamped);
+        assert_eq!(result.value, 0.0);
+    }
+
+    #[test]
+    fn test_score_degenerate_peers() {
+        let datasets = vec![[0.0, 10.0].to_vec(), [0.0, 20.0].to_vec()];
+        let result = Variance::score(
+            &[0.0, 10.0],
+            &datasets,
+            &Weighting::Uniform,
+            &Normalization::RelativeToMax,
+        );
+        assert_eq!(result.outcome, Outcome::DegeneratePeers);
+        assert_eq!(result.value, 0.0);
+    }
 }